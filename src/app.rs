@@ -1,4 +1,4 @@
-use arpa::{ARPAError, pipeline::Status};
+use arpa::{ARPAError, data_types::PulsarMeta, pipeline::Status};
 use egui::{Align, FontId, Layout};
 use log::{debug, error, info, warn};
 
@@ -11,9 +11,11 @@ mod toas;
 use ephemerides::EphemerideApp;
 use helpers::{
     ICON_CROSS, ICON_REVERT, ICON_SAVE, IconicButton, StatusMessage,
-    StatusMessageSeverity, confirm_button, icon,
+    StatusMessageSeverity, confirm_button,
+    downloader::{FetchType, Fingerprints},
+    icon,
 };
-use pulsars::PulsarsApp;
+use pulsars::{Duplicate, PulsarsApp};
 use toas::TOAsApp;
 
 mod syncher;
@@ -74,7 +76,7 @@ impl Application {
         })
     }
 
-    pub(crate) fn init(self, cc: &eframe::CreationContext<'_>) -> Self {
+    pub(crate) fn init(mut self, cc: &eframe::CreationContext<'_>) -> Self {
         use egui::FontFamily as FF;
         use egui::TextStyle as TS;
         let text_styles: std::collections::BTreeMap<_, _> = [
@@ -91,6 +93,13 @@ impl Application {
             style.text_styles = text_styles.clone();
         });
 
+        if let Some(storage) = cc.storage {
+            self.pulsars.downloader.load_layout(storage);
+            self.pulsars.load_mappings(storage);
+            self.ephemerides.downloader.load_layout(storage);
+            self.toas.downloader.load_layout(storage);
+        }
+
         self
     }
 
@@ -184,41 +193,78 @@ impl Application {
                 self.error(&err);
                 self.pulsars.reset_ui();
                 self.ephemerides.reset_ui();
+                self.toas.reset_ui();
                 self.pipeline.reset();
             }
             Message::Connected => self.info(&"Connected!"),
             Message::CommitSuccess => {
                 self.info(&"Commit successful! (list not updated)");
+                self.pulsars.downloader.clear_edits();
 
                 self.has_live_transaction = false;
             }
             Message::RollbackSuccess => {
                 self.info(&"Rollback successful!");
+
+                // Inline edits were undone, so the list is out of date
+                if self.pulsars.downloader.clear_edits() {
+                    self.archivist.request(Request::Resync(DataType::Pulsar));
+                }
                 self.has_live_transaction = false;
             }
             Message::ItemAdded(dt, id) => {
                 self.info(&format!("Successfully added {dt} #{id}"));
                 self.reset_part(&dt);
+                self.forget_related();
                 self.has_live_transaction = true;
             }
             Message::ItemDeleted(dt, id) => {
                 self.info(&format!("Successfully deleted {dt} #{id}"));
                 self.reset_part(&dt);
+                self.forget_related();
                 self.has_live_transaction = true;
             }
             Message::ItemUpdated(dt, id) => {
                 self.info(&format!("Successfully updated {dt} #{id}"));
+                self.forget_related();
                 self.has_live_transaction = true;
             }
+            Message::PulsarRelated(..)
+            | Message::EphemerideRelated(..)
+            | Message::TOARelated(..)
+            | Message::RelatedFailed(..) => self.set_related(message),
+
+            Message::Fingerprints(dt, fingerprints) => {
+                self.check_fingerprints(dt, fingerprints);
+            }
+            Message::PulsarChanges(changes) => {
+                self.pulsars.downloader.merge(changes);
+            }
+            Message::EphemerideChanges(changes) => {
+                self.ephemerides.downloader.merge(changes);
+            }
+            Message::TOAChanges(changes) => {
+                self.toas.downloader.merge(changes);
+            }
+
+            Message::PulsarDuplicates(pulsar, duplicates) => {
+                self.check_duplicates(pulsar, duplicates);
+            }
+            Message::PulsarDependents(_)
+            | Message::MergePreview(_)
+            | Message::PulsarEphemerides(..)
+            | Message::PulsarTOAs(..)
+            | Message::ParPaths(_) => {
+                self.pulsars.handle_message(message, &self.archivist);
+            }
+            Message::PulsarsMerged(keep, lose) => self.merged(keep, lose),
             Message::Pulsars(pulsars) => {
                 if pulsars.is_empty() {
                     self.warn(&"No pulsars to download!");
                 }
                 self.pulsars.downloader.set(pulsars);
             }
-            Message::SinglePulsar(pulsar) => {
-                self.pulsars.downloader.add(pulsar);
-            }
+            Message::SinglePulsar(pulsar) => self.pulsars.add(pulsar),
 
             Message::Ephemerides(pars) => {
                 if pars.is_empty() {
@@ -271,6 +317,97 @@ impl Application {
         self.pipeline.interrupt();
     }
 
+    /// A new pulsar was checked against the database. Those of a bulk add
+    /// go on with their par files; the rest are the Pulsars tab's to add.
+    fn check_duplicates(
+        &mut self,
+        pulsar: PulsarMeta,
+        duplicates: Vec<Duplicate>,
+    ) {
+        if self.ephemerides.awaits_check(&pulsar.alias) {
+            self.ephemerides
+                .add_checked(&self.archivist, pulsar, &duplicates);
+            return;
+        }
+
+        let message = Message::PulsarDuplicates(pulsar, duplicates);
+        self.pulsars.handle_message(message, &self.archivist);
+        // The user decides what to do with a duplicate there
+        if self.pulsars.checking_duplicates() {
+            self.tab = Tab::Pulsars;
+        }
+    }
+
+    /// Drops what the database no longer has, and fetches what changed.
+    fn check_fingerprints(&mut self, dt: DataType, fingerprints: Fingerprints) {
+        let stale = match dt {
+            DataType::Pulsar => self.pulsars.downloader.check(fingerprints),
+            DataType::Ephemeride => {
+                self.ephemerides.downloader.check(fingerprints)
+            }
+            DataType::Toa => self.toas.downloader.check(fingerprints),
+        };
+        if !stale.is_empty() {
+            self.archivist.request(Request::Refetch(dt, stale));
+        }
+    }
+
+    /// Hands fetched related records, or why they couldn't be, to their tab.
+    fn set_related(&mut self, message: Message) {
+        match message {
+            Message::PulsarRelated(id, related) => {
+                self.pulsars.downloader.set_related(id, related);
+            }
+            Message::EphemerideRelated(id, related) => {
+                self.ephemerides.downloader.set_related(id, related);
+            }
+            Message::TOARelated(id, related) => {
+                self.toas.downloader.set_related(id, related);
+            }
+            Message::RelatedFailed(DataType::Pulsar, id, err) => {
+                self.pulsars.downloader.related_failed(id, err);
+            }
+            Message::RelatedFailed(DataType::Ephemeride, id, err) => {
+                self.ephemerides.downloader.related_failed(id, err);
+            }
+            Message::RelatedFailed(DataType::Toa, id, err) => {
+                self.toas.downloader.related_failed(id, err);
+            }
+            _ => {}
+        }
+    }
+
+    /// Related records may be stale after any change.
+    fn forget_related(&mut self) {
+        self.pulsars.downloader.forget_related();
+        self.pulsars.forget_fetched();
+        self.ephemerides.downloader.forget_related();
+        self.toas.downloader.forget_related();
+    }
+
+    /// Shows the pulsar in its tab, fetching it if it isn't loaded.
+    fn move_to_pulsar(&mut self, id: i32) {
+        self.tab = Tab::Pulsars;
+        if !self.pulsars.select_with_id(id) {
+            // Arrives selected
+            self.archivist.request(Request::Download(
+                DataType::Pulsar,
+                FetchType::Id(id),
+            ));
+        }
+    }
+
+    /// Ephemerides and TOAs changed hands, so every list is resynced.
+    fn merged(&mut self, keep: i32, lose: i32) {
+        self.info(&format!("Merged pulsar #{lose} into #{keep}"));
+        self.forget_related();
+        self.has_live_transaction = true;
+
+        for dt in [DataType::Pulsar, DataType::Ephemeride, DataType::Toa] {
+            self.archivist.request(Request::Resync(dt));
+        }
+    }
+
     fn reset_part(&mut self, dt: &DataType) {
         match dt {
             DataType::Pulsar => self.pulsars.deselect(),
@@ -294,16 +431,33 @@ impl eframe::App for Application {
 
         // ---- Display current applet ----------------------------------------
         match self.tab {
-            Tab::Pulsars => self.pulsars.show(ctx, &self.archivist),
+            Tab::Pulsars => {
+                self.pulsars.show(ctx, &self.archivist);
+                if let Some(id) = self.pulsars.select_ephemeride() {
+                    self.tab = Tab::Ephemerides;
+                    if !self.ephemerides.select_with_id(id) {
+                        // Arrives selected
+                        self.archivist.request(Request::Download(
+                            DataType::Ephemeride,
+                            FetchType::Id(id),
+                        ));
+                    }
+                }
+            }
             Tab::Ephemerides => {
-                self.ephemerides.show(ctx, &self.archivist);
+                let pulsars = self.pulsars.downloader.data();
+                self.ephemerides.show(ctx, &self.archivist, pulsars);
                 if let Some(id) = self.ephemerides.select_pulsar() {
-                    self.tab = Tab::Pulsars;
-                    self.pulsars.select_with_id(id);
+                    self.move_to_pulsar(id);
                 }
             }
 
-            Tab::TOAs => self.toas.show(ctx, &self.archivist),
+            Tab::TOAs => {
+                self.toas.show(ctx, &self.archivist);
+                if let Some(id) = self.toas.select_pulsar() {
+                    self.move_to_pulsar(id);
+                }
+            }
 
             Tab::Pipeline => {
                 self.pipeline.show(ctx, &self.archivist, &self.ephemerides);
@@ -317,5 +471,13 @@ impl eframe::App for Application {
 
         // Collect any and all messasges
         self.messages.append(self.pulsars.messages());
+        self.messages.append(self.ephemerides.messages());
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.pulsars.downloader.save_layout(storage);
+        self.pulsars.save_mappings(storage);
+        self.ephemerides.downloader.save_layout(storage);
+        self.toas.downloader.save_layout(storage);
     }
 }
@@ -1,15 +1,27 @@
+use arpa::data_types::PulsarMeta;
 use egui::RichText;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::app::{
     Request, Syncher,
     helpers::{
-        ICON_INSERT, ICON_OPEN, ICON_WRITE, IconicButton, StatusMessage,
-        StatusMessageSeverity, confirm_button,
-        downloader::{Downloader, DownloaderAction},
+        ICON_FOLDER, ICON_INSERT, ICON_OPEN, ICON_WRITE, IconicButton,
+        StatusMessage, StatusMessageSeverity, confirm_button, detail_row,
+        downloader::{self, Downloader, DownloaderAction, ItemColumn},
     },
+    pulsars::{self, Duplicate, find_duplicates},
 };
 
+mod bulk;
+mod compare;
+mod par_file;
+mod viewer;
+
+use bulk::{BulkAction, BulkAdd, NewPar};
+use compare::ParCompare;
+use par_file::ParFile;
+use viewer::ParViewer;
+
 const DATA_TYPE: crate::app::DataType = crate::app::DataType::Ephemeride;
 
 #[derive(Debug)]
@@ -18,48 +30,92 @@ pub struct ParData {
     pub pulsar_id: i32,
     pub pulsar_name: String,
     pub path: String,
+    pub checksum: String,
+}
+
+/// What an ephemeride refers to.
+#[derive(Debug)]
+pub struct ParRelated {
+    pub pulsar: PulsarMeta,
 }
-impl super::helpers::downloader::Item for ParData {
+impl downloader::Item for ParData {
     const NAME: &str = "ephemerides";
-    const COLUMNS: &[(&str, &str)] = &[
-        ("ID", "The automatically generated ID."),
-        ("Pulsar", "The name of the pulsar referred to."),
-        ("Pulsar ID", "The ID of the pulsar referred to."),
-        ("Path", "The path to the file."),
+    const COLUMNS: &[ItemColumn<Self>] = &[
+        ItemColumn {
+            header: "ID",
+            hint: "The automatically generated ID.",
+            cell: |p, ui| {
+                ui.label(p.id.to_string());
+            },
+            text: |p| p.id.to_string(),
+            edit: None,
+            cmp: |a, b| a.id.cmp(&b.id),
+        },
+        ItemColumn {
+            header: "Pulsar",
+            hint: "The name of the pulsar referred to.",
+            cell: |p, ui| {
+                ui.label(&p.pulsar_name);
+            },
+            text: |p| p.pulsar_name.clone(),
+            edit: None,
+            cmp: |a, b| a.pulsar_name.cmp(&b.pulsar_name),
+        },
+        ItemColumn {
+            header: "Pulsar ID",
+            hint: "The ID of the pulsar referred to.",
+            cell: |p, ui| {
+                ui.label(p.pulsar_id.to_string());
+            },
+            text: |p| p.pulsar_id.to_string(),
+            edit: None,
+            cmp: |a, b| a.pulsar_id.cmp(&b.pulsar_id),
+        },
+        ItemColumn {
+            header: "Path",
+            hint: "The path to the file.",
+            cell: |p, ui| {
+                ui.label(&p.path);
+            },
+            text: |p| p.path.clone(),
+            edit: None,
+            cmp: |a, b| a.path.cmp(&b.path),
+        },
     ];
+    const LINKS: &[&str] = &["Pulsar"];
+
+    type Related = ParRelated;
 
     fn id(&self) -> i32 {
         self.id
     }
 
-    fn cmp_by(&self, other: &Self, index: usize) -> std::cmp::Ordering {
-        match index {
-            0 => self.id.cmp(&other.id),
-            1 => self.pulsar_name.cmp(&other.pulsar_name),
-            2 => self.pulsar_id.cmp(&other.pulsar_id),
-            3 => self.path.cmp(&other.path),
-            _ => std::cmp::Ordering::Equal,
-        }
+    fn details(&self, ui: &mut egui::Ui) {
+        detail_row(ui, "ID", self.id.to_string());
+        detail_row(ui, "Pulsar", &self.pulsar_name);
+        detail_row(ui, "Pulsar ID", self.pulsar_id.to_string());
+        detail_row(ui, "Path", &self.path);
+        detail_row(ui, "Checksum", &self.checksum);
     }
 
-    fn format(&self, row: &mut egui_extras::TableRow) {
-        // id
-        row.col(|ui| {
-            ui.label(self.id.to_string());
-        });
-
-        // pulsar
-        row.col(|ui| {
-            ui.label(&self.pulsar_name);
-        });
-        row.col(|ui| {
-            ui.label(self.pulsar_id.to_string());
-        });
-
-        // path
-        row.col(|ui| {
-            ui.label(self.path.to_string());
-        });
+    fn related(&self, related: &Self::Related, ui: &mut egui::Ui) {
+        let pulsar = &related.pulsar;
+        egui::Grid::new("par_related_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                detail_row(ui, "Pulsar", RichText::new(&pulsar.alias).strong());
+                detail_row(ui, "Pulsar ID", pulsar.id.to_string());
+                detail_row(
+                    ui,
+                    "Master",
+                    if pulsar.master_parfile_id == Some(self.id) {
+                        "Yes"
+                    } else {
+                        "No"
+                    },
+                );
+            });
     }
 }
 
@@ -70,6 +126,14 @@ pub struct EphemerideApp {
     new_par: Option<PathBuf>,
     new_par_pid: String,
     new_par_mastery: bool,
+    /// The pulsar the par file names, when none of the loaded ones match.
+    new_par_pulsar: Option<PulsarMeta>,
+    bulk: Option<BulkAdd>,
+    /// Par files of bulk-added pulsars that are being checked for
+    /// duplicates, keyed by the pulsar's alias.
+    bulk_pending: Vec<NewPar>,
+    viewer: ParViewer,
+    compare: ParCompare,
 
     move_to_pulsar_id: Option<i32>,
 }
@@ -83,16 +147,33 @@ impl EphemerideApp {
             new_par: None,
             new_par_pid: String::new(),
             new_par_mastery: false,
+            new_par_pulsar: None,
+            bulk: None,
+            bulk_pending: Vec::new(),
+            viewer: ParViewer::new(),
+            compare: ParCompare::new(),
 
             move_to_pulsar_id: None,
         }
     }
 
-    pub fn show(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        archivist: &Syncher,
+        pulsars: &[PulsarMeta],
+    ) {
         self.downloader.action_bar(ctx);
+        self.downloader.detail_panel(ctx);
+        self.bulk_modal(ctx, archivist);
+
+        let selected = self.downloader.selected();
+        let data = self.downloader.data();
+        self.viewer.show(ctx, selected.map(|i| &data[i]));
+        self.compare.show(ctx, data);
 
         match self.downloader.action() {
-            DownloaderAction::None => {}
+            DownloaderAction::None | DownloaderAction::Edit { .. } => {}
             DownloaderAction::Delete(index) => match index {
                 Some(id) => {
                     archivist.request(Request::DeleteItem(DATA_TYPE, id));
@@ -107,6 +188,19 @@ impl EphemerideApp {
                 let request = Request::Download(DATA_TYPE, ft);
                 archivist.request(request);
             }
+
+            DownloaderAction::Link(id) => {
+                let item = self.downloader.get(id);
+                self.move_to_pulsar_id = item.map(|d| d.pulsar_id);
+            }
+
+            DownloaderAction::Related(id) => {
+                archivist.request(Request::Related(DATA_TYPE, id));
+            }
+
+            DownloaderAction::Resync => {
+                archivist.request(Request::Resync(DATA_TYPE));
+            }
         }
 
         let response = egui::CentralPanel::default()
@@ -115,7 +209,7 @@ impl EphemerideApp {
                     egui::UiBuilder::new().sense(egui::Sense::click()),
                     |ui| {
                         egui::Frame::default()
-                            .show(ui, |ui| self.body(ui, archivist))
+                            .show(ui, |ui| self.body(ui, archivist, pulsars))
                     },
                 )
                 .response
@@ -126,26 +220,43 @@ impl EphemerideApp {
             self.downloader.deselect();
         }
 
-        ctx.input(|i| {
-            if let Some(df) = i.raw.dropped_files.first() {
-                self.new_par.clone_from(&df.path);
-            }
+        let mut dropped = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|df| df.path.clone())
+                .collect::<Vec<_>>()
         });
+        if dropped.len() > 1 {
+            let source = format!("{} dropped files", dropped.len());
+            self.bulk = Some(BulkAdd::new(source, dropped, pulsars));
+        } else if let Some(path) = dropped.pop() {
+            if path.is_dir() {
+                self.bulk_from_dir(&path, pulsars);
+            } else {
+                self.set_new_par(Some(path), pulsars);
+            }
+        }
     }
 
-    fn body(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
+    fn body(
+        &mut self,
+        ui: &mut egui::Ui,
+        archivist: &Syncher,
+        pulsars: &[PulsarMeta],
+    ) {
         ui.heading(RichText::new("Ephemerides").strong());
         ui.add_space(12.0);
 
         ui.add_space(16.0);
-        self.par_data_entry(ui);
-        self.par_data_controls(ui, archivist);
+        self.par_data_entry(ui, archivist);
+        self.par_data_controls(ui, archivist, pulsars);
 
         ui.separator();
         self.downloader.table(ui);
     }
 
-    fn par_data_entry(&mut self, ui: &mut egui::Ui) {
+    fn par_data_entry(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
         egui::Grid::new("new_par_grid")
             .num_columns(2)
             .spacing([32.0, 4.0])
@@ -164,6 +275,33 @@ impl EphemerideApp {
 
                 ui.end_row();
 
+                if self.new_par_pid.is_empty()
+                    && let Some(pulsar) = &self.new_par_pulsar
+                {
+                    ui.label("");
+                    let create = ui
+                        .horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "No loaded pulsar matches {}",
+                                    pulsar.alias
+                                ))
+                                .italics(),
+                            );
+                            ui.button("Create it")
+                                .on_hover_text(
+                                    "Add the pulsar, with RAJ and DECJ from \
+                                    the par file.",
+                                )
+                                .clicked()
+                        })
+                        .inner;
+                    if create {
+                        self.create_pulsar(archivist);
+                    }
+                    ui.end_row();
+                }
+
                 ui.label("Master").on_hover_text(
                     "Whether or not this should be set as the \
                         pulsar's master ephemeride.",
@@ -175,12 +313,28 @@ impl EphemerideApp {
             });
     }
 
-    fn par_data_controls(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
+    fn par_data_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        archivist: &Syncher,
+        pulsars: &[PulsarMeta],
+    ) {
         ui.horizontal(|ui| {
             let load =
                 ui.add(IconicButton::new(ICON_OPEN).on_hover_text("Load file"));
             if load.clicked() {
-                self.new_par = rfd::FileDialog::new().pick_file();
+                let path = rfd::FileDialog::new().pick_file();
+                self.set_new_par(path, pulsars);
+            }
+
+            let folder = ui.add(
+                IconicButton::new(ICON_FOLDER)
+                    .on_hover_text("Add a directory of par files"),
+            );
+            if folder.clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder()
+            {
+                self.bulk_from_dir(&dir, pulsars);
             }
 
             let insert = ui.add(
@@ -231,11 +385,182 @@ impl EphemerideApp {
                     master: self.new_par_mastery,
                 });
             }
+
+            ui.separator();
+            ui.toggle_value(&mut self.viewer.open, "View par file");
+            if ui.selectable_label(self.compare.open, "Compare").clicked() {
+                if self.compare.open {
+                    self.compare.open = false;
+                } else {
+                    self.compare.start(self.downloader.selected_id());
+                }
+            }
         });
     }
 
+    /// Takes a new par file, and fills in the pulsar it names if one of the
+    /// loaded pulsars matches.
+    fn set_new_par(&mut self, path: Option<PathBuf>, pulsars: &[PulsarMeta]) {
+        self.new_par = path;
+        self.new_par_pulsar = None;
+        let Some(path) = &self.new_par else {
+            return;
+        };
+
+        let pulsar = match ParFile::read(path) {
+            Ok(par) => par.pulsar(),
+            Err(err) => {
+                self.messages.push(StatusMessage {
+                    severity: StatusMessageSeverity::Warning,
+                    message: format!("Cannot read {}! {err}", path.display()),
+                });
+                return;
+            }
+        };
+        let Some(pulsar) = pulsar else {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Warning,
+                message: format!("{} names no pulsar.", path.display()),
+            });
+            return;
+        };
+
+        if let Some(found) = find_duplicates(&pulsar, pulsars).first() {
+            self.new_par_pid.clone_from(&found.pulsar.alias);
+        } else {
+            self.new_par_pid.clear();
+            self.new_par_pulsar = Some(pulsar);
+        }
+    }
+
+    fn bulk_from_dir(&mut self, dir: &Path, pulsars: &[PulsarMeta]) {
+        match BulkAdd::from_dir(dir, pulsars) {
+            Ok(bulk) => self.bulk = Some(bulk),
+            Err(err) => self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Warning,
+                message: format!("Cannot read {}! {err}", dir.display()),
+            }),
+        }
+    }
+
+    /// Shows the par files waiting to be added, if any.
+    fn bulk_modal(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        let Some(bulk) = &mut self.bulk else {
+            return;
+        };
+
+        match bulk.show(ctx) {
+            BulkAction::None => return,
+            BulkAction::Cancel => {}
+            BulkAction::Add { pulsars, pars } => {
+                self.add_bulk(archivist, pulsars, pars);
+            }
+        }
+        self.bulk = None;
+    }
+
+    /// Checks the new pulsars against the database, and adds the par files
+    /// of the loaded ones. The others wait for their pulsar's check.
+    fn add_bulk(
+        &mut self,
+        archivist: &Syncher,
+        new: Vec<PulsarMeta>,
+        pars: Vec<NewPar>,
+    ) {
+        let (mut failed, mut checked) = (Vec::new(), Vec::new());
+        for mut pulsar in new {
+            let alias = pulsar.alias.clone();
+            if let Err(err) = pulsars::validate(&mut pulsar) {
+                self.messages.push(StatusMessage {
+                    severity: StatusMessageSeverity::Warning,
+                    message: format!("Cannot add {alias}! {err}"),
+                });
+                failed.push(alias);
+                continue;
+            }
+            archivist.request(Request::CheckPulsar(pulsar));
+            checked.push(alias);
+        }
+
+        for par in pars.into_iter().filter(|p| !failed.contains(&p.pulsar)) {
+            if checked.contains(&par.pulsar) {
+                self.bulk_pending.push(par);
+            } else {
+                add_par(archivist, par);
+            }
+        }
+    }
+
+    /// Whether a bulk add waits on the check of the pulsar with this alias.
+    pub fn awaits_check(&self, alias: &str) -> bool {
+        self.bulk_pending.iter().any(|p| p.pulsar == alias)
+    }
+
+    /// Adds a bulk-added pulsar unless the database already has it, and then
+    /// its par files, to whichever of the two it is.
+    pub fn add_checked(
+        &mut self,
+        archivist: &Syncher,
+        pulsar: PulsarMeta,
+        duplicates: &[Duplicate],
+    ) {
+        let alias = pulsar.alias.clone();
+        let key = if let Some(existing) = duplicates.first() {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Warning,
+                message: format!(
+                    "{} is already in the database as #{}, so its \
+                    ephemerides go there.",
+                    pulsar.alias, existing.pulsar.id,
+                ),
+            });
+            existing.pulsar.id.to_string()
+        } else {
+            archivist.request(Request::AddPulsar(pulsar));
+            alias.clone()
+        };
+
+        let (pars, rest) = std::mem::take(&mut self.bulk_pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| p.pulsar == alias);
+        self.bulk_pending = rest;
+        for par in pars {
+            add_par(
+                archivist,
+                NewPar {
+                    pulsar: key.clone(),
+                    ..par
+                },
+            );
+        }
+    }
+
+    /// Adds the pulsar the par file names, and picks it. Like pulsars added
+    /// in their own tab, it's checked for duplicates first.
+    fn create_pulsar(&mut self, archivist: &Syncher) {
+        let Some(mut pulsar) = self.new_par_pulsar.take() else {
+            return;
+        };
+
+        if let Err(err) = pulsars::validate(&mut pulsar) {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Warning,
+                message: format!("Cannot add {}! {err}", pulsar.alias),
+            });
+            return;
+        }
+
+        self.new_par_pid.clone_from(&pulsar.alias);
+        archivist.request(Request::CheckPulsar(pulsar));
+    }
+
+    pub const fn messages(&mut self) -> &mut Vec<StatusMessage> {
+        &mut self.messages
+    }
+
     pub fn reset_ui(&mut self) {
         self.downloader.stop_fetching();
+        self.bulk_pending.clear();
     }
 
     pub fn deselect(&mut self) {
@@ -246,7 +571,29 @@ impl EphemerideApp {
         self.move_to_pulsar_id.take()
     }
 
+    /// Selects the ephemeride, if loaded, as if its row was clicked.
+    pub(crate) fn select_with_id(&mut self, id: i32) -> bool {
+        if self.downloader.selected_id() == Some(id) {
+            return true;
+        }
+
+        let data = self.downloader.data();
+        let Some(index) = data.iter().position(|p| p.id == id) else {
+            return false;
+        };
+        self.downloader.select(index);
+        true
+    }
+
     pub(crate) fn selected(&self) -> Option<i32> {
         self.downloader.selected_id()
     }
 }
+
+fn add_par(archivist: &Syncher, par: NewPar) {
+    archivist.request(Request::AddPar {
+        path: par.path,
+        pulsar: par.pulsar,
+        master: par.master,
+    });
+}
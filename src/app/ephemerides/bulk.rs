@@ -0,0 +1,395 @@
+//! Adding many par files at once, from a directory or a drop.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use arpa::{ARPAError, data_types::PulsarMeta};
+use egui::RichText;
+use egui_extras::{Column, TableBuilder};
+
+use super::ParFile;
+use crate::app::{helpers::MISSING_DATA, pulsars::find_duplicates};
+
+/// The pulsar a par file is for.
+enum Target {
+    Loaded(PulsarMeta),
+    /// None of the loaded pulsars match. Including the file adds it, unless
+    /// the database has it after all.
+    New(PulsarMeta),
+}
+
+impl Target {
+    const fn pulsar(&self) -> &PulsarMeta {
+        match self {
+            Self::Loaded(pulsar) | Self::New(pulsar) => pulsar,
+        }
+    }
+
+    /// What `Request::AddPar` finds the pulsar by.
+    fn key(&self) -> String {
+        match self {
+            Self::Loaded(pulsar) => pulsar.id.to_string(),
+            Self::New(pulsar) => pulsar.alias.clone(),
+        }
+    }
+}
+
+/// The MJD of the Unix epoch.
+const UNIX_MJD: f64 = 40_587.0;
+
+/// How new a par file is, as an MJD. A file's own date beats any modification
+/// time, as copying files around changes the latter.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Newness {
+    /// When the file was last modified.
+    Modified(f64),
+    /// The FINISH of the data it was fitted to, or else its PEPOCH.
+    Dated(f64),
+}
+
+/// One par file, and the pulsar it names.
+struct BulkRow {
+    path: PathBuf,
+    newness: Option<Newness>,
+    result: Result<Target, String>,
+    include: bool,
+}
+
+impl BulkRow {
+    fn new(path: PathBuf, pulsars: &[PulsarMeta]) -> Self {
+        let par = ParFile::read(&path).map_err(|err| err.to_string());
+        let newness = par
+            .as_ref()
+            .ok()
+            .and_then(|par| {
+                par.number("FINISH").or_else(|| par.number("PEPOCH"))
+            })
+            .map(Newness::Dated)
+            .or_else(|| modified(&path).map(Newness::Modified));
+        let result = par
+            .and_then(|par| {
+                par.pulsar().ok_or_else(|| "Names no pulsar".into())
+            })
+            .map(|pulsar| {
+                let found =
+                    find_duplicates(&pulsar, pulsars).into_iter().next();
+                found.map_or(Target::New(pulsar), |d| Target::Loaded(d.pulsar))
+            });
+
+        Self {
+            path,
+            newness,
+            include: result.is_ok(),
+            result,
+        }
+    }
+}
+
+/// A par file to add.
+pub struct NewPar {
+    pub path: PathBuf,
+    /// The pulsar's id, or alias if it's new.
+    pub pulsar: String,
+    pub master: bool,
+}
+
+/// What the user decided in the review.
+pub enum BulkAction {
+    None,
+    Cancel,
+    Add {
+        /// Pulsars that files name but none is loaded, to check against the
+        /// database before their files are added.
+        pulsars: Vec<PulsarMeta>,
+        pars: Vec<NewPar>,
+    },
+}
+
+/// A batch of par files, waiting to be looked over before they are added.
+pub struct BulkAdd {
+    source: String,
+    rows: Vec<BulkRow>,
+    /// Whether the newest included file of each pulsar becomes its master.
+    newest_master: bool,
+}
+
+impl BulkAdd {
+    pub fn new(
+        source: String,
+        paths: Vec<PathBuf>,
+        pulsars: &[PulsarMeta],
+    ) -> Self {
+        Self {
+            source,
+            rows: paths
+                .into_iter()
+                .map(|path| BulkRow::new(path, pulsars))
+                .collect(),
+            newest_master: false,
+        }
+    }
+
+    /// Every `.par` file in a directory.
+    pub fn from_dir(
+        dir: &Path,
+        pulsars: &[PulsarMeta],
+    ) -> Result<Self, ARPAError> {
+        let mut paths = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| is_par(path))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        Ok(Self::new(dir.display().to_string(), paths, pulsars))
+    }
+
+    /// For each row, whether it becomes its pulsar's master. Files without a
+    /// date or modification time can't be the newest, so they never are.
+    fn masters(&self) -> Vec<bool> {
+        let mut masters = vec![false; self.rows.len()];
+        if !self.newest_master {
+            return masters;
+        }
+
+        let mut newest = HashMap::<String, usize>::new();
+        for (i, row) in self.rows.iter().enumerate().filter(|(_, r)| r.include)
+        {
+            let (Ok(target), Some(_)) = (&row.result, row.newness) else {
+                continue;
+            };
+            let newest = newest.entry(target.key()).or_insert(i);
+            if row.newness > self.rows[*newest].newness {
+                *newest = i;
+            }
+        }
+
+        for i in newest.into_values() {
+            masters[i] = true;
+        }
+        masters
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> BulkAction {
+        let mut action = BulkAction::None;
+
+        let modal = egui::Modal::new(egui::Id::new("par_bulk_add"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading(format!("Add ephemerides from {}", self.source));
+            ui.add_space(8.0);
+
+            let valid = self.rows.iter().filter(|r| r.result.is_ok()).count();
+            let new = self
+                .rows
+                .iter()
+                .filter(|r| matches!(r.result, Ok(Target::New(_))))
+                .count();
+            ui.label(format!(
+                "{} files, {valid} valid, {new} for pulsars that aren't \
+                loaded.",
+                self.rows.len(),
+            ));
+
+            ui.horizontal(|ui| {
+                if ui.button("Include all valid").clicked() {
+                    for row in &mut self.rows {
+                        row.include = row.result.is_ok();
+                    }
+                }
+                if ui.button("Include none").clicked() {
+                    for row in &mut self.rows {
+                        row.include = false;
+                    }
+                }
+            });
+            ui.checkbox(
+                &mut self.newest_master,
+                "Make the newest file of each pulsar its master",
+            )
+            .on_hover_text(
+                "Newest by the FINISH or PEPOCH in the file, or else by \
+                modification time",
+            );
+            ui.separator();
+
+            self.table(ui);
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let count = self.rows.iter().filter(|r| r.include).count();
+                let add = ui.add_enabled(
+                    count > 0,
+                    egui::Button::new(format!("Add {count}")),
+                );
+                if add.clicked() {
+                    action = self.included();
+                }
+                if ui.button("Cancel").clicked() {
+                    action = BulkAction::Cancel;
+                }
+            });
+        });
+
+        if response.should_close() {
+            action = BulkAction::Cancel;
+        }
+
+        action
+    }
+
+    fn table(&mut self, ui: &mut egui::Ui) {
+        let height = ui.ctx().screen_rect().height() * 0.6;
+        let masters = self.masters();
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .max_scroll_height(height)
+            .column(Column::auto())
+            .columns(Column::auto().at_least(64.0), 4)
+            .column(Column::remainder().at_least(160.0))
+            .header(20.0, |mut header| {
+                for title in ["", "File", "MJD", "Pulsar", "Master", "Status"]
+                {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, self.rows.len(), |mut row| {
+                    let index = row.index();
+                    let item = &mut self.rows[index];
+
+                    row.col(|ui| {
+                        ui.add_enabled(
+                            item.result.is_ok(),
+                            egui::Checkbox::without_text(&mut item.include),
+                        );
+                    });
+                    row.col(|ui| {
+                        let name = item.path.file_name().unwrap_or_default();
+                        ui.label(name.to_string_lossy())
+                            .on_hover_text(item.path.display().to_string());
+                    });
+                    row.col(|ui| match item.newness {
+                        Some(Newness::Dated(mjd)) => {
+                            ui.label(format!("{mjd:.0}"))
+                                .on_hover_text("FINISH, or else PEPOCH");
+                        }
+                        Some(Newness::Modified(mjd)) => {
+                            ui.label(RichText::new(format!("{mjd:.0}")).weak())
+                                .on_hover_text("Modification time");
+                        }
+                        None => {
+                            ui.label(MISSING_DATA);
+                        }
+                    });
+                    row.col(|ui| {
+                        if let Ok(target) = &item.result {
+                            let pulsar = target.pulsar();
+                            ui.label(RichText::new(&pulsar.alias).strong());
+                        }
+                    });
+                    row.col(|ui| {
+                        if masters[index] {
+                            ui.label("Yes");
+                        }
+                    });
+
+                    row.col(|ui| match &item.result {
+                        Err(err) => {
+                            ui.colored_label(ui.visuals().error_fg_color, err);
+                        }
+                        Ok(Target::Loaded(pulsar)) => {
+                            ui.label(format!("OK, #{}", pulsar.id));
+                        }
+                        Ok(Target::New(_)) => {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                "Not loaded; including it adds the pulsar if it's new",
+                            );
+                        }
+                    });
+                });
+            });
+    }
+
+    /// What to add, new pulsars first.
+    fn included(&self) -> BulkAction {
+        let masters = self.masters();
+        let mut pulsars = Vec::<PulsarMeta>::new();
+        let mut pars = Vec::new();
+
+        for (row, master) in self.rows.iter().zip(masters) {
+            let (true, Ok(target)) = (row.include, &row.result) else {
+                continue;
+            };
+            if let Target::New(pulsar) = target
+                && !pulsars.iter().any(|p| p.alias == pulsar.alias)
+            {
+                pulsars.push(pulsar.clone());
+            }
+
+            pars.push(NewPar {
+                path: row.path.clone(),
+                pulsar: target.key(),
+                master,
+            });
+        }
+
+        BulkAction::Add { pulsars, pars }
+    }
+}
+
+fn is_par(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("par"))
+}
+
+/// When a file was last modified, as an MJD.
+fn modified(path: &Path) -> Option<f64> {
+    let time = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs_f64();
+    Some(seconds / 86400.0 + UNIX_MJD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(newness: Option<Newness>) -> BulkRow {
+        BulkRow {
+            path: PathBuf::from("J0437-4715.par"),
+            newness,
+            result: Ok(Target::Loaded(PulsarMeta::null())),
+            include: true,
+        }
+    }
+
+    #[test]
+    fn a_files_own_date_beats_its_modification_time() {
+        let bulk = BulkAdd {
+            source: String::new(),
+            rows: vec![
+                row(Some(Newness::Modified(60_500.0))),
+                row(Some(Newness::Dated(59_000.0))),
+                row(Some(Newness::Dated(60_000.0))),
+            ],
+            newest_master: true,
+        };
+        assert_eq!(bulk.masters(), [false, false, true]);
+    }
+
+    #[test]
+    fn files_without_a_date_are_never_master() {
+        let bulk = BulkAdd {
+            source: String::new(),
+            rows: vec![row(None), row(Some(Newness::Modified(0.0))), row(None)],
+            newest_master: true,
+        };
+        assert_eq!(bulk.masters(), [false, true, false]);
+    }
+}
@@ -0,0 +1,363 @@
+//! What changed between two ephemerides, parameter by parameter.
+
+use std::{collections::HashMap, path::Path};
+
+use egui::RichText;
+use egui_extras::{Column, TableBuilder};
+
+use super::{
+    ParData,
+    par_file::{ParFile, ParParam},
+};
+
+/// Further than this many sigmas, a change stands out.
+const SIGNIFICANT: f64 = 3.0;
+
+/// A parameter that differs between the old and the new file.
+struct Difference {
+    name: String,
+    old: Option<ParParam>,
+    new: Option<ParParam>,
+}
+
+impl Difference {
+    /// The change in value, in units of the new uncertainty, or the old one
+    /// if the new file gives none.
+    fn sigmas(&self) -> Option<f64> {
+        let (old, new) = (self.old.as_ref()?, self.new.as_ref()?);
+        let sigma = new.sigma().or_else(|| old.sigma())?;
+        Some((new.number()? - old.number()?) / sigma)
+    }
+
+    fn value_changed(&self) -> bool {
+        let (Some(old), Some(new)) = (&self.old, &self.new) else {
+            return false;
+        };
+        // `1.0` and `1.00` are the same value
+        match (old.number(), new.number()) {
+            (Some(a), Some(b)) => a.total_cmp(&b).is_ne(),
+            _ => old.value != new.value,
+        }
+    }
+
+    fn fit_changed(&self) -> bool {
+        let (Some(old), Some(new)) = (&self.old, &self.new) else {
+            return false;
+        };
+        old.fit.unwrap_or(false) != new.fit.unwrap_or(false)
+    }
+}
+
+/// A parameter's name, case aside, and how many of that name come before it
+/// in its file.
+type Key = (String, usize);
+
+/// Repeated names, like several `JUMP`s, pair up in the order they come.
+fn keyed(par: &ParFile) -> Vec<(Key, &ParParam)> {
+    let mut seen = HashMap::<String, usize>::new();
+    par.params
+        .iter()
+        .map(|param| {
+            let name = param.name.to_ascii_uppercase();
+            let count = seen.entry(name.clone()).or_default();
+            let key = (name, *count);
+            *count += 1;
+            (key, param)
+        })
+        .collect()
+}
+
+/// The parameters that differ, in the order of the old file, followed by
+/// what the new one adds. Names match regardless of case, as in
+/// [`ParFile::get`].
+fn diff(old: &ParFile, new: &ParFile) -> Vec<Difference> {
+    let (old, new) = (keyed(old), keyed(new));
+    let find = |params: &[(Key, &ParParam)], key: &Key| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, param)| (*param).clone())
+    };
+
+    let mut keys = Vec::<(&Key, &str)>::new();
+    for (key, param) in old.iter().chain(&new) {
+        if !keys.iter().any(|(k, _)| *k == key) {
+            keys.push((key, &param.name));
+        }
+    }
+
+    keys.into_iter()
+        .map(|(key, name)| Difference {
+            name: name.to_string(),
+            old: find(&old, key),
+            new: find(&new, key),
+        })
+        .filter(|d| {
+            d.old.is_none()
+                || d.new.is_none()
+                || d.value_changed()
+                || d.fit_changed()
+        })
+        .collect()
+}
+
+/// Reads both files, and compares them.
+fn compare(
+    pars: &[ParData],
+    old: i32,
+    new: i32,
+) -> Result<Vec<Difference>, String> {
+    let read = |id: i32| {
+        let par = pars
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("Ephemeride #{id} isn't loaded."))?;
+        ParFile::read(Path::new(&par.path))
+            .map_err(|err| format!("#{id}: {err}"))
+    };
+
+    Ok(diff(&read(old)?, &read(new)?))
+}
+
+/// Picks two ephemerides, and shows how they differ.
+pub struct ParCompare {
+    pub open: bool,
+    /// Ids of the old and the new ephemeride.
+    picked: [Option<i32>; 2],
+    /// The pair last compared, and how it went.
+    compared: Option<[i32; 2]>,
+    differences: Result<Vec<Difference>, String>,
+}
+
+impl ParCompare {
+    pub const fn new() -> Self {
+        Self {
+            open: false,
+            picked: [None, None],
+            compared: None,
+            differences: Ok(Vec::new()),
+        }
+    }
+
+    /// Opens the window with the given ephemeride as the old one.
+    pub const fn start(&mut self, id: Option<i32>) {
+        self.open = true;
+        if id.is_some() {
+            self.picked[0] = id;
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, pars: &[ParData]) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Compare ephemerides")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                self.picker(ui, pars);
+                ui.separator();
+
+                let [Some(old), Some(new)] = self.picked else {
+                    ui.label("Pick two ephemerides.");
+                    return;
+                };
+                if self.compared != Some([old, new]) {
+                    self.compared = Some([old, new]);
+                    self.differences = compare(pars, old, new);
+                }
+
+                match &self.differences {
+                    Ok(differences) => differences_table(ui, differences),
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+                }
+            });
+
+        self.open = open;
+    }
+
+    fn picker(&mut self, ui: &mut egui::Ui, pars: &[ParData]) {
+        egui::Grid::new("par_compare_pick_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                for (i, picked) in self.picked.iter_mut().enumerate() {
+                    ui.label(if i == 0 { "Old" } else { "New" });
+                    pick(ui, i, picked, pars);
+                    ui.end_row();
+                }
+            });
+
+        if ui.button("Swap").clicked() {
+            self.picked.swap(0, 1);
+        }
+    }
+}
+
+/// A drop-down of the loaded ephemerides.
+fn pick(
+    ui: &mut egui::Ui,
+    salt: usize,
+    picked: &mut Option<i32>,
+    pars: &[ParData],
+) {
+    let text = picked
+        .and_then(|id| pars.iter().find(|p| p.id == id))
+        .map_or_else(|| "(pick)".to_string(), label);
+
+    egui::ComboBox::from_id_salt(("par_compare_pick", salt))
+        .selected_text(text)
+        .height(320.0)
+        .show_ui(ui, |ui| {
+            for par in pars {
+                ui.selectable_value(picked, Some(par.id), label(par));
+            }
+        });
+}
+
+fn label(par: &ParData) -> String {
+    format!("#{} {} {}", par.id, par.pulsar_name, par.path)
+}
+
+fn differences_table(ui: &mut egui::Ui, differences: &[Difference]) {
+    if differences.is_empty() {
+        ui.label(RichText::new("The files set the same parameters.").italics());
+        return;
+    }
+
+    let count = |f: fn(&Difference) -> bool| {
+        differences.iter().filter(|d| f(d)).count()
+    };
+    ui.label(format!(
+        "{} added, {} removed, {} changed value, {} changed fit.",
+        count(|d| d.old.is_none()),
+        count(|d| d.new.is_none()),
+        count(Difference::value_changed),
+        count(Difference::fit_changed),
+    ));
+
+    TableBuilder::new(ui)
+        .striped(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::auto().at_least(80.0))
+        .columns(Column::auto().at_least(140.0), 2)
+        .column(Column::auto().at_least(64.0))
+        .column(Column::remainder().at_least(64.0))
+        .header(20.0, |mut header| {
+            for title in ["Name", "Old", "New", "Change", "Fit"] {
+                header.col(|ui| {
+                    ui.strong(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, differences.len(), |mut row| {
+                let difference = &differences[row.index()];
+                row.col(|ui| {
+                    ui.label(RichText::new(&difference.name).strong());
+                });
+                for param in [&difference.old, &difference.new] {
+                    row.col(|ui| value(ui, param.as_ref()));
+                }
+                row.col(|ui| change(ui, difference));
+                row.col(|ui| {
+                    if difference.fit_changed()
+                        && let (Some(old), Some(new)) =
+                            (&difference.old, &difference.new)
+                    {
+                        ui.label(format!("{} → {}", fit(old), fit(new)));
+                    }
+                });
+            });
+        });
+}
+
+fn value(ui: &mut egui::Ui, param: Option<&ParParam>) {
+    match param {
+        Some(param) => {
+            let text = param.uncertainty.as_ref().map_or_else(
+                || param.value.clone(),
+                |u| format!("{} ± {u}", param.value),
+            );
+            ui.monospace(text);
+        }
+        None => {
+            ui.label(RichText::new("absent").italics());
+        }
+    }
+}
+
+fn change(ui: &mut egui::Ui, difference: &Difference) {
+    match (&difference.old, &difference.new) {
+        (None, _) => {
+            ui.label("Added");
+        }
+        (_, None) => {
+            ui.label("Removed");
+        }
+        _ if difference.value_changed() => match difference.sigmas() {
+            Some(sigmas) if sigmas.abs() > SIGNIFICANT => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{sigmas:+.2}σ"),
+                );
+            }
+            Some(sigmas) => {
+                ui.label(format!("{sigmas:+.2}σ"));
+            }
+            None => {
+                ui.label("Changed").on_hover_text("No uncertainty to go by");
+            }
+        },
+        _ => {}
+    }
+}
+
+const fn fit(param: &ParParam) -> &'static str {
+    if matches!(param.fit, Some(true)) {
+        "fit"
+    } else {
+        "fixed"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_match_regardless_of_case() {
+        let old = ParFile::parse("F0 173.6879458 1\nPSRJ J0437-4715");
+        let new = ParFile::parse("f0 173.6879458 1\npsrj J0437-4715");
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn repeated_names_pair_in_order() {
+        let old = ParFile::parse("JUMP -f L-wide 0.1 1\nJUMP -f L-wide 0.2 1");
+        let new = ParFile::parse("JUMP -f L-wide 0.1 1\nJUMP -f L-wide 0.3 1");
+        let differences = diff(&old, &new);
+        assert_eq!(differences.len(), 1);
+        let value = |p: &Option<ParParam>| p.as_ref().map(|p| p.value.clone());
+        assert_eq!(value(&differences[0].old).as_deref(), Some("0.2"));
+        assert_eq!(value(&differences[0].new).as_deref(), Some("0.3"));
+    }
+
+    #[test]
+    fn repeated_names_added_at_the_end() {
+        let old = ParFile::parse("JUMP -f L-wide 0.1 1");
+        let new = ParFile::parse("JUMP -f L-wide 0.1 1\nJUMP -f L-wide 0.2 1");
+        let differences = diff(&old, &new);
+        assert_eq!(differences.len(), 1);
+        assert!(differences[0].old.is_none());
+        assert_eq!(
+            differences[0].new.as_ref().map(|p| p.value.as_str()),
+            Some("0.2")
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Reading the parameters out of a par file.
+
+use std::path::Path;
+
+use arpa::{ARPAError, data_types::PulsarMeta};
+
+use crate::app::{helpers::coordinates, pulsars};
+
+/// Seconds in a Julian year.
+const YEAR: f64 = 365.25 * 86400.0;
+
+/// One `NAME VALUE [FIT] [UNCERTAINTY]` line.
+#[derive(Debug, Clone)]
+pub struct ParParam {
+    /// Includes the selector of parameters like `JUMP -f L-wide`.
+    pub name: String,
+    pub value: String,
+    pub fit: Option<bool>,
+    pub uncertainty: Option<String>,
+}
+
+impl ParParam {
+    /// The value as a number, in the units of its uncertainty: seconds of
+    /// time for RAJ, and arcseconds for DECJ.
+    pub fn number(&self) -> Option<f64> {
+        let name = &self.name;
+        if name.eq_ignore_ascii_case("RAJ") {
+            coordinates::parse_ra(&self.value).ok().map(|d| d * 240.0)
+        } else if name.eq_ignore_ascii_case("DECJ") {
+            coordinates::parse_dec(&self.value).ok().map(|d| d * 3600.0)
+        } else {
+            parse_number(&self.value)
+        }
+    }
+
+    pub fn sigma(&self) -> Option<f64> {
+        parse_number(self.uncertainty.as_ref()?).filter(|s| *s > 0.0)
+    }
+}
+
+/// What follows from the spin and binary parameters, where they're given.
+#[derive(Debug, Clone, Default)]
+pub struct Derived {
+    /// In seconds.
+    pub period: Option<f64>,
+    pub period_dot: Option<f64>,
+    /// Characteristic age, in years.
+    pub age: Option<f64>,
+    /// Surface magnetic field, in gauss.
+    pub b_field: Option<f64>,
+    /// In days.
+    pub binary_period: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParFile {
+    pub params: Vec<ParParam>,
+}
+
+impl ParFile {
+    pub fn read(path: &Path) -> Result<Self, ARPAError> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let params = text
+            .lines()
+            .filter(|l| !is_comment(l))
+            .filter_map(|l| parse_line(l.split_whitespace()))
+            .collect();
+        Self { params }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .map(|p| p.value.as_str())
+    }
+
+    /// A value as a number.
+    pub fn number(&self, name: &str) -> Option<f64> {
+        parse_number(self.get(name)?)
+    }
+
+    pub fn derived(&self) -> Derived {
+        let (period, period_dot) = match self.number("F0") {
+            Some(f0) if f0 > 0.0 => {
+                (Some(1.0 / f0), self.number("F1").map(|f1| -f1 / (f0 * f0)))
+            }
+            _ => (
+                self.number("P0").or_else(|| self.number("P")),
+                self.number("P1"),
+            ),
+        };
+
+        // Only spin-down makes for an age and a field
+        let spin_down = period.zip(period_dot.filter(|pd| *pd > 0.0));
+        Derived {
+            period,
+            period_dot,
+            age: spin_down.map(|(p, pd)| p / (2.0 * pd) / YEAR),
+            b_field: spin_down.map(|(p, pd)| 3.2e19 * (p * pd).sqrt()),
+            binary_period: self.number("PB").or_else(|| {
+                let fb0 = self.number("FB0").filter(|f| *f > 0.0)?;
+                Some(1.0 / fb0 / 86400.0)
+            }),
+        }
+    }
+
+    /// The pulsar the file is for, with its names and position. PSRJ and
+    /// PSRB are the J and B names; a bare PSR is whichever it looks like.
+    pub fn pulsar(&self) -> Option<PulsarMeta> {
+        let psr = self.get("PSR");
+        let named = |prefix: char, key: &str| {
+            self.get(key)
+                .or_else(|| psr.filter(|p| p.starts_with(prefix)))
+                .map(ToString::to_string)
+        };
+        let j_name = named('J', "PSRJ");
+        let b_name = named('B', "PSRB");
+
+        // A bare PSR of neither shape is still a name
+        let alias = psr
+            .filter(|_| j_name.is_none() && b_name.is_none())
+            .map(ToString::to_string);
+        pulsars::make_pulsar(
+            alias,
+            j_name,
+            b_name,
+            self.get("RAJ").map(ToString::to_string),
+            self.get("DECJ").map(ToString::to_string),
+        )
+    }
+}
+
+/// Understands Fortran's `D` exponents too.
+fn parse_number(text: &str) -> Option<f64> {
+    text.replace(['D', 'd'], "E").parse().ok()
+}
+
+/// Blank lines, and `#` or tempo's `C ` comments.
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty() || line.starts_with('#') || line.starts_with("C ")
+}
+
+/// Whether a word is a flag like `-f` or `-sys`, rather than a negative
+/// value like `-47:15:09.1`.
+fn is_flag(word: &str) -> bool {
+    word.strip_prefix('-')
+        .is_some_and(|w| w.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+fn parse_line<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Option<ParParam> {
+    let mut name = words.next()?.to_string();
+    let mut value = words.next()?;
+    // Flag selectors, as in `JUMP -f L-wide 0.1 1`, belong to the name
+    while is_flag(value) {
+        name = format!("{name} {value} {}", words.next()?);
+        value = words.next()?;
+    }
+
+    let rest = words.collect::<Vec<_>>();
+    let (fit, uncertainty) = match rest.as_slice() {
+        [] => (None, None),
+        [flag, more @ ..] if matches!(*flag, "0" | "1") => {
+            (Some(*flag == "1"), more.first())
+        }
+        [uncertainty, ..] => (None, Some(uncertainty)),
+    };
+
+    Some(ParParam {
+        name,
+        value: value.to_string(),
+        fit,
+        uncertainty: uncertainty.map(ToString::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> ParParam {
+        parse_line(line.split_whitespace()).expect("a parameter")
+    }
+
+    #[test]
+    fn negative_declination_is_a_value() {
+        let param = parse("DECJ -47:15:09.1 1 0.2");
+        assert_eq!(param.name, "DECJ");
+        assert_eq!(param.value, "-47:15:09.1");
+        assert_eq!(param.fit, Some(true));
+        assert_eq!(param.uncertainty.as_deref(), Some("0.2"));
+    }
+
+    #[test]
+    fn flag_selector_belongs_to_the_name() {
+        let param = parse("JUMP -f L-wide 0.1 1");
+        assert_eq!(param.name, "JUMP -f L-wide");
+        assert_eq!(param.value, "0.1");
+        assert_eq!(param.fit, Some(true));
+        assert_eq!(param.uncertainty, None);
+    }
+
+    #[test]
+    fn fortran_exponents() {
+        let param = parse("F1 -1.2D-15 1 3D-20");
+        assert_eq!(param.name, "F1");
+        assert_eq!(param.value, "-1.2D-15");
+        assert_eq!(param.fit, Some(true));
+        assert_eq!(param.number(), Some(-1.2e-15));
+        assert_eq!(param.sigma(), Some(3e-20));
+    }
+
+    #[test]
+    fn coordinates_in_any_case() {
+        let param = parse("decj -00:30:00 1 0.2");
+        assert_eq!(param.number(), Some(-1800.0));
+    }
+}
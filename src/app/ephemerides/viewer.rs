@@ -0,0 +1,201 @@
+//! Looking inside the selected ephemeride's par file.
+
+use egui::RichText;
+use egui_extras::{Column, TableBuilder};
+
+use super::{
+    ParData,
+    par_file::{Derived, ParFile},
+};
+use crate::app::helpers::{ICON_CHECK, MISSING_DATA, detail_row};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewerTab {
+    Parameters,
+    Derived,
+    Raw,
+}
+
+/// A par file as read from disk.
+struct Shown {
+    id: i32,
+    path: String,
+    read: Result<(String, ParFile, Derived), String>,
+}
+
+impl Shown {
+    fn read(par: &ParData) -> Self {
+        let read = std::fs::read_to_string(&par.path)
+            .map(|text| {
+                let parsed = ParFile::parse(&text);
+                let derived = parsed.derived();
+                (text, parsed, derived)
+            })
+            .map_err(|err| err.to_string());
+
+        Self {
+            id: par.id,
+            path: par.path.clone(),
+            read,
+        }
+    }
+}
+
+/// A window following the selected ephemeride.
+pub struct ParViewer {
+    pub open: bool,
+    tab: ViewerTab,
+    shown: Option<Shown>,
+}
+
+impl ParViewer {
+    pub const fn new() -> Self {
+        Self {
+            open: false,
+            tab: ViewerTab::Parameters,
+            shown: None,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, selected: Option<&ParData>) {
+        if !self.open {
+            return;
+        }
+
+        match selected {
+            Some(par)
+                if self.shown.as_ref().is_some_and(|s| s.id == par.id) => {}
+            Some(par) => self.shown = Some(Shown::read(par)),
+            None => self.shown = None,
+        }
+
+        let mut open = self.open;
+        let mut reload = false;
+        egui::Window::new("Par file")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                let Some(shown) = &self.shown else {
+                    ui.label("Select an ephemeride to see its par file.");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.strong(format!("#{} {}", shown.id, shown.path));
+                    reload = ui.small_button("Reload").clicked();
+                });
+                let (text, par, derived) = match &shown.read {
+                    Ok(read) => read,
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                        return;
+                    }
+                };
+
+                ui.horizontal(|ui| {
+                    for (tab, title) in [
+                        (ViewerTab::Parameters, "Parameters"),
+                        (ViewerTab::Derived, "Derived"),
+                        (ViewerTab::Raw, "Raw"),
+                    ] {
+                        ui.selectable_value(&mut self.tab, tab, title);
+                    }
+                });
+                ui.separator();
+
+                match self.tab {
+                    ViewerTab::Parameters => parameters(ui, par),
+                    ViewerTab::Derived => derived_grid(ui, derived),
+                    ViewerTab::Raw => raw(ui, text),
+                }
+            });
+
+        if reload {
+            self.shown = None;
+        }
+        self.open = open;
+    }
+}
+
+fn parameters(ui: &mut egui::Ui, par: &ParFile) {
+    TableBuilder::new(ui)
+        .striped(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(Column::auto().at_least(80.0))
+        .column(Column::auto().at_least(160.0))
+        .column(Column::auto())
+        .column(Column::remainder().at_least(80.0))
+        .header(20.0, |mut header| {
+            for title in ["Name", "Value", "Fit", "Uncertainty"] {
+                header.col(|ui| {
+                    ui.strong(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, par.params.len(), |mut row| {
+                let param = &par.params[row.index()];
+                row.col(|ui| {
+                    ui.label(RichText::new(&param.name).strong());
+                });
+                row.col(|ui| {
+                    ui.monospace(&param.value);
+                });
+                row.col(|ui| {
+                    if param.fit == Some(true) {
+                        ui.label(ICON_CHECK).on_hover_text("Fitted");
+                    }
+                });
+                row.col(|ui| {
+                    ui.monospace(
+                        param.uncertainty.as_deref().unwrap_or_default(),
+                    );
+                });
+            });
+        });
+}
+
+fn derived_grid(ui: &mut egui::Ui, derived: &Derived) {
+    let show = |value: Option<f64>, format: fn(f64) -> String| {
+        value.map_or_else(|| MISSING_DATA.to_string(), format)
+    };
+
+    egui::Grid::new("par_derived_grid")
+        .num_columns(2)
+        .spacing([16.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            detail_row(ui, "P", show(derived.period, |p| format!("{p:.12} s")));
+            detail_row(
+                ui,
+                "Ṗ",
+                show(derived.period_dot, |pd| format!("{pd:.4e} s/s")),
+            );
+            detail_row(
+                ui,
+                "Characteristic age",
+                show(derived.age, |age| format!("{age:.3e} yr")),
+            );
+            detail_row(
+                ui,
+                "Surface B-field",
+                show(derived.b_field, |b| format!("{b:.3e} G")),
+            );
+            detail_row(
+                ui,
+                "Binary period",
+                show(derived.binary_period, |pb| format!("{pb:.6} d")),
+            );
+        });
+}
+
+fn raw(ui: &mut egui::Ui, text: &str) {
+    egui::ScrollArea::both().show(ui, |ui| {
+        ui.add(
+            egui::TextEdit::multiline(&mut &*text)
+                .code_editor()
+                .desired_width(f32::INFINITY),
+        );
+    });
+}
@@ -1,6 +1,7 @@
-use egui::{Align, Layout, RichText, Ui};
+use egui::{Align, Layout, RichText, Ui, WidgetText};
 use std::fmt::Display;
 
+pub mod coordinates;
 pub mod downloader;
 mod iconic_button;
 
@@ -15,10 +16,12 @@ pub const ICON_DELETE: &str = "🗑";
 pub const ICON_WRITE: &str = "📝";
 pub const ICON_SAVE: &str = "💾";
 pub const ICON_OPEN: &str = "🗁";
+pub const ICON_FOLDER: &str = "📁";
 pub const ICON_ARROW: &str = "⤵";
 pub const ICON_REVERT: &str = "⮪";
 pub const ICON_SYNC: &str = "🔄";
 pub const ICON_RUN: &str = "🚂";
+pub const ICON_DETAILS: &str = "ℹ";
 
 pub struct StatusMessage {
     pub severity: StatusMessageSeverity,
@@ -69,6 +72,12 @@ where
     }
 }
 
+/// Empty text means no value.
+pub fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
 /// Returns whether the data changed or not.
 pub fn enter_data_option(ui: &mut Ui, data: &mut Option<String>) -> bool {
     if let Some(str) = data {
@@ -92,6 +101,13 @@ pub fn enter_data_option(ui: &mut Ui, data: &mut Option<String>) -> bool {
     }
 }
 
+/// Adds a labelled row to a two-column grid.
+pub fn detail_row(ui: &mut Ui, label: &str, value: impl Into<WidgetText>) {
+    ui.label(label);
+    ui.label(value);
+    ui.end_row();
+}
+
 /// Adds a pop-up to confirm button press.
 pub fn confirm_button(
     button: &egui::response::Response,
@@ -120,8 +136,9 @@ pub fn icon(text: &str) -> RichText {
     RichText::new(text).size(52.0)
 }
 
-/// Adds a delete button aligned to the right.
-pub fn ra_delete(ui: &mut Ui, enabled: bool) -> bool {
+/// Adds a delete button aligned to the right. If `ask` is set, the
+/// confirmation opens as if the button was clicked.
+pub fn ra_delete(ui: &mut Ui, enabled: bool, ask: bool) -> bool {
     ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
         let delete = ui.add(
             IconicButton::new(ICON_DELETE)
@@ -129,6 +146,13 @@ pub fn ra_delete(ui: &mut Ui, enabled: bool) -> bool {
                 .on_hover_text("Delete"),
         );
 
+        if ask && enabled {
+            egui::Popup::open_id(
+                ui.ctx(),
+                egui::Popup::default_response_id(&delete),
+            );
+        }
+
         confirm_button(&delete, "Delete selected?")
     })
     .inner
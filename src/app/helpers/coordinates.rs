@@ -0,0 +1,334 @@
+//! J2000 coordinates, as stored (sexagesimal text) and as numbers (degrees).
+
+use arpa::ARPAError;
+
+/// Where the table's coordinate display choice is kept in egui memory.
+const DEGREES_ID: &str = "coordinates-in-degrees";
+
+/// Seconds decimals when the input had none to go by.
+const RA_DECIMALS: usize = 4;
+const DEC_DECIMALS: usize = 3;
+/// More would overflow the rounding.
+const MAX_DECIMALS: usize = 9;
+
+/// Parses a right ascension, as `hh:mm:ss.s` or decimal degrees, into
+/// degrees.
+pub fn parse_ra(text: &str) -> Result<f64, ARPAError> {
+    parse(text, false).map(|(degrees, _)| degrees)
+}
+
+/// Parses a declination, as `±dd:mm:ss.s` or decimal degrees, into degrees.
+pub fn parse_dec(text: &str) -> Result<f64, ARPAError> {
+    parse(text, true).map(|(degrees, _)| degrees)
+}
+
+/// Rewrites a right ascension as zero-padded `hh:mm:ss.s`. Sexagesimal
+/// input keeps its precision.
+pub fn normalize_ra(text: &str) -> Result<String, ARPAError> {
+    let (degrees, decimals) = parse(text, false)?;
+    Ok(format_ra(degrees, decimals.unwrap_or(RA_DECIMALS)))
+}
+
+/// Rewrites a declination as zero-padded `±dd:mm:ss.s`. Sexagesimal input
+/// keeps its precision.
+pub fn normalize_dec(text: &str) -> Result<String, ARPAError> {
+    let (degrees, decimals) = parse(text, true)?;
+    Ok(format_dec(degrees, decimals.unwrap_or(DEC_DECIMALS)))
+}
+
+pub fn format_ra(degrees: f64, decimals: usize) -> String {
+    let (_, h, m, s) = split(degrees / 15.0, decimals);
+    // Rounding may wrap around
+    let h = h % 24;
+    format!("{h:02}:{m:02}:{}", pad_seconds(s, decimals))
+}
+
+pub fn format_dec(degrees: f64, decimals: usize) -> String {
+    let (negative, d, m, s) = split(degrees, decimals);
+    let sign = if negative { '-' } else { '+' };
+    format!("{sign}{d:02}:{m:02}:{}", pad_seconds(s, decimals))
+}
+
+/// Whether tables show coordinates in degrees rather than sexagesimal.
+pub fn in_degrees(ctx: &egui::Context) -> bool {
+    ctx.data_mut(|d| d.get_persisted(egui::Id::new(DEGREES_ID)))
+        .unwrap_or(false)
+}
+
+pub fn set_in_degrees(ctx: &egui::Context, degrees: bool) {
+    ctx.data_mut(|d| d.insert_persisted(egui::Id::new(DEGREES_ID), degrees));
+}
+
+/// Shows a stored coordinate the way the user wants it. Unparsable text is
+/// shown as is.
+pub fn display(
+    ctx: &egui::Context,
+    text: &str,
+    parse: fn(&str) -> Result<f64, ARPAError>,
+) -> String {
+    if !in_degrees(ctx) {
+        return text.to_string();
+    }
+    parse(text).map_or_else(|_| text.to_string(), |d| format!("{d:.5}°"))
+}
+
+/// Orders stored coordinates by value. Missing or broken ones go last.
+pub fn cmp(
+    a: Option<&String>,
+    b: Option<&String>,
+    parse: fn(&str) -> Result<f64, ARPAError>,
+) -> std::cmp::Ordering {
+    let a = a.and_then(|t| parse(t).ok());
+    let b = b.and_then(|t| parse(t).ok());
+    cmp_values(a, b)
+}
+
+/// Orders numbers, with missing ones last.
+pub fn cmp_values(a: Option<f64>, b: Option<f64>) -> std::cmp::Ordering {
+    match (a, b) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (Some(a), Some(b)) => a.total_cmp(&b),
+    }
+}
+
+/// Equatorial to Galactic, J2000.
+const GALACTIC: [[f64; 3]; 3] = [
+    [-0.054_875_560_4, -0.873_437_090_2, -0.483_835_015_5],
+    [0.494_109_427_9, -0.444_829_630_0, 0.746_982_244_5],
+    [-0.867_666_149_0, -0.198_076_373_4, 0.455_983_776_2],
+];
+
+/// Obliquity of the ecliptic at J2000, in degrees.
+const OBLIQUITY: f64 = 23.439_291_1;
+
+/// Galactic longitude and latitude, in degrees, from J2000 RA and DEC.
+pub fn galactic(ra: f64, dec: f64) -> (f64, f64) {
+    let v = unit(ra, dec);
+    let rotated =
+        GALACTIC.map(|row| row.iter().zip(v).map(|(m, x)| m * x).sum());
+    spherical(rotated)
+}
+
+/// Ecliptic longitude and latitude, in degrees, from J2000 RA and DEC.
+pub fn ecliptic(ra: f64, dec: f64) -> (f64, f64) {
+    let [x, y, z] = unit(ra, dec);
+    let (sin, cos) = OBLIQUITY.to_radians().sin_cos();
+    spherical([x, y.mul_add(cos, z * sin), z.mul_add(cos, -y * sin)])
+}
+
+/// The conventional J name: `Jhhmm±ddmm`, truncated rather than rounded.
+pub fn j_name(ra: f64, dec: f64) -> String {
+    let (h, m) = truncate(ra / 15.0).unwrap_or_default();
+    let (d, dm) = truncate(dec.abs()).unwrap_or_default();
+    let sign = if dec < 0.0 { '-' } else { '+' };
+    format!("J{:02}{m:02}{sign}{d:02}{dm:02}", h % 24)
+}
+
+/// The conventional B name: `Bhhmm±dd` in B1950 coordinates.
+pub fn b_name(ra: f64, dec: f64) -> String {
+    let (ra, dec) = b1950(ra, dec);
+    let (h, m) = truncate(ra / 15.0).unwrap_or_default();
+    let (d, _) = truncate(dec.abs()).unwrap_or_default();
+    let sign = if dec < 0.0 { '-' } else { '+' };
+    format!("B{:02}{m:02}{sign}{d:02}", h % 24)
+}
+
+/// Whole units and whole minutes, without rounding.
+fn truncate(value: f64) -> Option<(u64, u64)> {
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    // A hair up, so exact minutes don't floor to the one below
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let minutes = value.mul_add(60.0, 1e-9).floor() as u64;
+    Some((minutes / 60, minutes % 60))
+}
+
+/// Precesses J2000 to B1950, in degrees. Ignores the FK4 E-terms, which are
+/// well below what names resolve.
+fn b1950(ra: f64, dec: f64) -> (f64, f64) {
+    // Julian centuries from J2000 to B1950
+    let centuries: f64 = -0.500_002_1;
+    let arcsec = |a: f64, b: f64, c: f64| {
+        let t = centuries;
+        (t * c).mul_add(t * t, (t * b).mul_add(t, a * t)) / 3600.0
+    };
+    let zeta = arcsec(2306.2181, 0.30188, 0.017_998).to_radians();
+    let z = arcsec(2306.2181, 1.09468, 0.018_203).to_radians();
+    let theta = arcsec(2004.3109, -0.42665, -0.041_833).to_radians();
+
+    let (sin_d, cos_d) = dec.to_radians().sin_cos();
+    let (sin_a, cos_a) = (ra.to_radians() + zeta).sin_cos();
+    let (sin_t, cos_t) = theta.sin_cos();
+
+    let y = cos_d * sin_a;
+    let x = (cos_t * cos_d).mul_add(cos_a, -(sin_t * sin_d));
+    let sin_dec = (sin_t * cos_d).mul_add(cos_a, cos_t * sin_d);
+
+    let ra = (y.atan2(x) + z).to_degrees().rem_euclid(360.0);
+    (ra, sin_dec.clamp(-1.0, 1.0).asin().to_degrees())
+}
+
+/// Angle between two positions, all in degrees.
+pub fn separation(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    // Vincenty, which holds up at both small and large angles
+    let (sin1, cos1) = lat1.to_radians().sin_cos();
+    let (sin2, cos2) = lat2.to_radians().sin_cos();
+    let (sin_d, cos_d) = (lon2 - lon1).to_radians().sin_cos();
+
+    let x = cos2 * sin_d;
+    let y = cos1.mul_add(sin2, -(sin1 * cos2 * cos_d));
+    let z = sin1.mul_add(sin2, cos1 * cos2 * cos_d);
+    x.hypot(y).atan2(z).to_degrees()
+}
+
+fn unit(lon: f64, lat: f64) -> [f64; 3] {
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat]
+}
+
+fn spherical([x, y, z]: [f64; 3]) -> (f64, f64) {
+    let lon = y.atan2(x).to_degrees().rem_euclid(360.0);
+    let lat = z.clamp(-1.0, 1.0).asin().to_degrees();
+    (lon, lat)
+}
+
+/// Returns degrees, and the number of seconds decimals if sexagesimal.
+fn parse(text: &str, dec: bool) -> Result<(f64, Option<usize>), ARPAError> {
+    let name = if dec {
+        "declination"
+    } else {
+        "right ascension"
+    };
+    let bad = |why: &str| {
+        ARPAError::MalformedInput(format!("Bad {name} \"{text}\": {why}"))
+    };
+
+    let trimmed = text.trim().trim_end_matches('°');
+    let mut parts = trimmed
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+
+    let (degrees, decimals) = if parts.len() == 1 {
+        let value = parts[0].parse::<f64>().map_err(|_| bad("not a number"))?;
+        (value, None)
+    } else {
+        if parts.len() > 3 {
+            return Err(bad("too many fields"));
+        }
+
+        let negative = parts[0].starts_with('-');
+        parts[0] = parts[0].strip_prefix(['+', '-']).unwrap_or(parts[0]);
+        if parts.iter().any(|p| p.contains(['+', '-'])) {
+            return Err(bad("only the first field can have a sign"));
+        }
+
+        let mut fields = [0.0; 3];
+        for (field, part) in fields.iter_mut().zip(&parts) {
+            *field = part.parse::<f64>().map_err(|_| bad("not a number"))?;
+        }
+
+        let [whole, minutes, seconds] = fields;
+        if minutes >= 60.0 || seconds >= 60.0 {
+            return Err(bad("minutes and seconds must be below 60"));
+        }
+        if !dec && whole >= 24.0 {
+            return Err(bad("hours must be below 24"));
+        }
+
+        let mut value = whole + minutes / 60.0 + seconds / 3600.0;
+        if !dec {
+            value *= 15.0;
+        }
+        if negative {
+            value = -value;
+        }
+
+        let decimals = parts
+            .get(2)
+            .and_then(|s| s.split_once('.'))
+            .map_or(0, |(_, d)| d.len());
+        (value, Some(decimals))
+    };
+
+    if !degrees.is_finite() {
+        return Err(bad("not a number"));
+    }
+    if dec && degrees.abs() > 90.0 {
+        return Err(bad("must be within ±90°"));
+    }
+    if !dec && !(0.0..360.0).contains(&degrees) {
+        return Err(bad("must be within 0° to 360°"));
+    }
+
+    Ok((degrees, decimals))
+}
+
+/// Splits into sign, whole, minutes and seconds. Rounds in whole units of
+/// the last decimal first, so seconds never show as 60.
+fn split(value: f64, decimals: usize) -> (bool, u64, u64, f64) {
+    let decimals = decimals.min(MAX_DECIMALS);
+    let scale = 10u64.pow(u32::try_from(decimals).unwrap_or(0));
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    #[allow(clippy::cast_precision_loss)]
+    let units = (value.abs() * 3600.0 * scale as f64).round() as u64;
+
+    let whole = units / (3600 * scale);
+    let minutes = units / (60 * scale) % 60;
+    #[allow(clippy::cast_precision_loss)]
+    let seconds = (units % (60 * scale)) as f64 / scale as f64;
+
+    (value < 0.0, whole, minutes, seconds)
+}
+
+fn pad_seconds(seconds: f64, decimals: usize) -> String {
+    let decimals = decimals.min(MAX_DECIMALS);
+    let width = if decimals == 0 { 2 } else { decimals + 3 };
+    format!("{seconds:0width$.decimals$}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn accepted_formats() {
+        for text in ["12:30:00", "12 30 00", "12:30", "187.5", "187.5°"] {
+            assert!(close(parse_ra(text).unwrap(), 187.5), "{text}");
+        }
+        for text in ["-30:15:00", "-30 15", "-30.25"] {
+            assert!(close(parse_dec(text).unwrap(), -30.25), "{text}");
+        }
+        assert!(close(parse_dec("+30:15").unwrap(), 30.25));
+        assert_eq!(normalize_ra("1:2:3.45").unwrap(), "01:02:03.45");
+        assert_eq!(normalize_dec("-0:30:00").unwrap(), "-00:30:00");
+    }
+
+    #[test]
+    fn out_of_range_is_rejected() {
+        for text in ["24:00:00", "12:60:00", "12:30:60", "360", "-1"] {
+            assert!(parse_ra(text).is_err(), "{text}");
+        }
+        for text in ["91", "-90:00:01", "10:60"] {
+            assert!(parse_dec(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn only_the_first_field_has_a_sign() {
+        for text in ["12:-30:00", "12:30:+10", "-12:-30:00", "--12:30"] {
+            assert!(parse_dec(text).is_err(), "{text}");
+        }
+        assert!(parse_ra("12:-30:00").is_err());
+        assert!(parse_ra("-1:00:00").is_err());
+    }
+}
@@ -1,18 +1,56 @@
-use egui::RichText;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+};
+
+use egui::{Key, RichText};
 use egui_extras::{Column, TableBuilder};
 use rayon::slice::ParallelSliceMut;
+use serde::{Deserialize, Serialize};
+
+use super::{ICON_DETAILS, ICON_SYNC, IconicButton, ra_delete};
+
+const ROW_HEIGHT: f32 = 18.0;
+/// How far PageUp/PageDown move the selection.
+const PAGE_ROWS: usize = 20;
+/// Seconds of no typing after which type-ahead starts over.
+const TYPE_AHEAD_TIMEOUT: f64 = 1.0;
+
+/// Describes one column of an `Item` table. Keeping the header, the cell
+/// and the comparator together means they cannot drift apart.
+pub struct ItemColumn<T> {
+    /// The header text.
+    pub header: &'static str,
+    /// Hover text for the header.
+    pub hint: &'static str,
+    /// Draws the cell for one item.
+    pub cell: fn(&T, &mut egui::Ui),
+    /// The cell as plain text, for searching and editing.
+    pub text: fn(&T) -> String,
+    /// Writes an edited cell back, for columns that can be edited inline.
+    pub edit: Option<fn(&mut T, String)>,
+    /// Orders two items by this column.
+    pub cmp: fn(&T, &T) -> Ordering,
+}
 
-use super::ICON_SYNC;
-
-use super::{IconicButton, ra_delete};
-
-pub trait Item: Send {
+pub trait Item: Send + Sized + 'static {
     const NAME: &str;
-    const COLUMNS: &[(&str, &str)];
+    const COLUMNS: &[ItemColumn<Self>];
+    /// Headers of the columns that start out hidden.
+    const HIDDEN: &[&str] = &[];
+    /// Headers of the columns shown as links, which lead to another record.
+    const LINKS: &[&str] = &[];
+
+    /// Records linked to an item, fetched separately when it is selected.
+    type Related;
 
     fn id(&self) -> i32;
-    fn format(&self, row: &mut egui_extras::TableRow);
-    fn cmp_by(&self, other: &Self, index: usize) -> std::cmp::Ordering;
+
+    /// Adds every field as rows of a two-column grid.
+    fn details(&self, ui: &mut egui::Ui);
+
+    /// Shows the records linked to an item.
+    fn related(&self, related: &Self::Related, ui: &mut egui::Ui);
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -22,18 +60,88 @@ pub enum FetchType {
     // Range(i32, i32),
 }
 
-#[derive(Clone, Copy)]
+/// Each row's id, with a hash of the row as the database has it.
+pub type Fingerprints = Vec<(i32, i64)>;
+
+/// How one column of `T::COLUMNS` is currently displayed.
+#[derive(Debug, Clone, Copy)]
+struct ColumnState {
+    index: usize,
+    visible: bool,
+    width: Option<f32>,
+}
+
+/// A column as stored between sessions. Columns are matched by header, so
+/// changes to `Item::COLUMNS` don't scramble a saved layout.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedColumn {
+    header: String,
+    visible: bool,
+    width: Option<f32>,
+}
+
+/// Payload for dragging a header onto another.
+struct ColumnDrag(usize);
+
+#[derive(Clone)]
 pub enum DownloaderAction {
     None,
     Delete(Option<i32>),
     Download(FetchType),
+    /// A cell of the item with this id was edited inline; the app decides
+    /// whether to keep it.
+    Edit {
+        id: i32,
+        column: usize,
+        value: String,
+    },
+    /// A link cell of the item with this id was clicked; the app knows where
+    /// it leads.
+    Link(i32),
+    /// Get the related records of an item.
+    Related(i32),
+    /// Get only what changed since the last sync.
+    Resync,
 }
 
-pub struct Downloader<T> {
+pub struct Downloader<T: Item> {
     data: Vec<T>,
 
+    /// Row fingerprints from the last sync, by id. `None` marks rows changed
+    /// here since, which are fetched again. Rows from a full download have
+    /// none, and are taken to be as the database has them.
+    fingerprints: BTreeMap<i32, Option<i64>>,
+    /// Fingerprints of the rows being fetched again.
+    pending: BTreeMap<i32, i64>,
+    /// How many resyncs have come back.
+    synced: u64,
+
     selected: Option<usize>,
-    sort_by: usize,
+    /// The column the user sorted by, if they did.
+    sort_by: Option<usize>,
+    /// Display order of the columns; empty until first shown or loaded.
+    layout: Vec<ColumnState>,
+    /// Whether the table should drop its widths on the next frame.
+    forget_widths: bool,
+
+    /// The cell being edited inline, as (item id, column, text).
+    editing: Option<(i32, usize, String)>,
+    /// Cells edited inline but not committed, as (item id, column).
+    edited: Vec<(i32, usize)>,
+
+    /// Only rows whose text in this column matches `filter` are shown.
+    filter_column: usize,
+    filter: String,
+
+    /// Set when the selection moved by keyboard, so the table follows. Counts
+    /// shown rows, not items.
+    scroll_to: Option<usize>,
+    type_ahead: String,
+    last_typed: f64,
+
+    show_details: bool,
+    related: Option<(i32, Result<T::Related, String>)>,
+    related_requested: Option<i32>,
 
     fetch_type: FetchType,
     fetching: bool,
@@ -47,9 +155,28 @@ where
     pub const fn new() -> Self {
         Self {
             data: Vec::new(),
+            fingerprints: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            synced: 0,
 
             selected: None,
-            sort_by: 0,
+            sort_by: None,
+            layout: Vec::new(),
+            forget_widths: false,
+
+            editing: None,
+            edited: Vec::new(),
+
+            filter_column: 0,
+            filter: String::new(),
+
+            scroll_to: None,
+            type_ahead: String::new(),
+            last_typed: 0.0,
+
+            show_details: false,
+            related: None,
+            related_requested: None,
 
             fetch_type: FetchType::All,
             fetching: false,
@@ -58,12 +185,24 @@ where
     }
 
     pub fn action_bar(&mut self, ctx: &egui::Context) {
+        let ask_delete = self.keyboard(ctx);
+
         egui::TopBottomPanel::bottom("downloader").show(ctx, |ui| {
             ui.add_space(12.0);
             ui.horizontal(|ui| {
                 self.download_menu(ui);
+                ui.separator();
+                self.filter_bar(ui);
+
+                let details = ui.add(
+                    IconicButton::new(ICON_DETAILS)
+                        .on_hover_text("Show or hide details"),
+                );
+                if details.clicked() {
+                    self.show_details = !self.show_details;
+                }
 
-                let delete = ra_delete(ui, self.selected.is_some());
+                let delete = ra_delete(ui, self.selected.is_some(), ask_delete);
                 if delete {
                     self.action = DownloaderAction::Delete(self.selected_id());
                 }
@@ -72,6 +211,138 @@ where
         });
     }
 
+    /// Moves the selection with arrows, PageUp/PageDown, Home/End, or by
+    /// typing the start of a value in the sorted column. Enter opens the
+    /// details. Returns whether Delete was pressed.
+    fn keyboard(&mut self, ctx: &egui::Context) -> bool {
+        let shown = self.shown();
+        if shown.is_empty() || ctx.wants_keyboard_input() {
+            return false;
+        }
+
+        // Moves are counted in shown rows
+        let last = shown.len() - 1;
+        let current = self.selected.and_then(|s| shown.binary_search(&s).ok());
+        let (mut target, enter, delete, typed, time) = ctx.input(|i| {
+            let target = if i.key_pressed(Key::ArrowDown) {
+                Some(current.map_or(0, |c| (c + 1).min(last)))
+            } else if i.key_pressed(Key::ArrowUp) {
+                Some(current.map_or(0, |c| c.saturating_sub(1)))
+            } else if i.key_pressed(Key::PageDown) {
+                Some(current.map_or(0, |c| (c + PAGE_ROWS).min(last)))
+            } else if i.key_pressed(Key::PageUp) {
+                Some(current.map_or(0, |c| c.saturating_sub(PAGE_ROWS)))
+            } else if i.key_pressed(Key::Home) {
+                Some(0)
+            } else if i.key_pressed(Key::End) {
+                Some(last)
+            } else {
+                None
+            };
+
+            let typed = i
+                .events
+                .iter()
+                .filter_map(|e| match e {
+                    egui::Event::Text(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect::<String>();
+
+            (
+                target,
+                i.key_pressed(Key::Enter),
+                i.key_pressed(Key::Delete),
+                typed,
+                i.time,
+            )
+        });
+
+        if !typed.is_empty() {
+            if time - self.last_typed > TYPE_AHEAD_TIMEOUT {
+                self.type_ahead.clear();
+            }
+            self.type_ahead.push_str(&typed.to_lowercase());
+            self.last_typed = time;
+
+            let text = T::COLUMNS[self.sort_by.unwrap_or(0)].text;
+            target = shown
+                .iter()
+                .position(|&i| {
+                    let text = text(&self.data[i]).to_lowercase();
+                    text.starts_with(&self.type_ahead)
+                })
+                .or(target);
+        }
+
+        if let Some(row) = target {
+            self.selected = Some(shown[row]);
+            self.scroll_to = Some(row);
+        }
+
+        if enter && self.selected.is_some() {
+            self.show_details = true;
+        }
+
+        delete && self.selected.is_some()
+    }
+
+    /// A collapsible side panel with everything about the selected item.
+    pub fn detail_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("details")
+            .resizable(true)
+            .default_width(280.0)
+            .show_animated(ctx, self.show_details, |ui| {
+                ui.add_space(12.0);
+
+                let Some(item) = self.selected.map(|i| &self.data[i]) else {
+                    ui.label(RichText::new("Nothing selected").italics());
+                    return;
+                };
+                let id = item.id();
+
+                ui.label(
+                    RichText::new(format!("{} #{id}", T::NAME))
+                        .strong()
+                        .text_style(egui::TextStyle::Button),
+                );
+                ui.separator();
+
+                egui::Grid::new("detail_grid")
+                    .num_columns(2)
+                    .spacing([16.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| item.details(ui));
+
+                ui.add_space(12.0);
+                ui.label(RichText::new("Related").strong());
+                ui.separator();
+
+                match &self.related {
+                    Some((rid, Ok(related))) if *rid == id => {
+                        egui::ScrollArea::vertical()
+                            .show(ui, |ui| item.related(related, ui));
+                    }
+                    Some((rid, Err(err))) if *rid == id => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("Cannot fetch related records! {err}"),
+                        );
+                        if ui.button("Retry").clicked() {
+                            self.forget_related();
+                        }
+                    }
+                    _ => {
+                        ui.spinner();
+                        if self.related_requested != Some(id) {
+                            self.related_requested = Some(id);
+                            self.action = DownloaderAction::Related(id);
+                        }
+                    }
+                }
+            });
+    }
+
     fn download_menu(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.set_height(IconicButton::HEIGHTS[1]);
@@ -86,10 +357,21 @@ where
                 ui.add(
                     IconicButton::new(ICON_SYNC)
                         .enabled(!self.fetching)
-                        .on_hover_text("Download pulsars"),
+                        .on_hover_text(
+                            "Download what changed since the last sync.\n\
+                            Right-click for a full reload.",
+                        ),
                 )
             };
 
+            download.context_menu(|ui| {
+                if ui.button("Reload everything").clicked() {
+                    self.fetching = true;
+                    self.action = DownloaderAction::Download(FetchType::All);
+                    ui.close();
+                }
+            });
+
             ui.radio_value(&mut self.fetch_type, FetchType::All, "All");
 
             let (mut id, enabled) = match self.fetch_type {
@@ -107,67 +389,372 @@ where
 
             if download.clicked() {
                 self.fetching = true;
-                self.action = DownloaderAction::Download(self.fetch_type);
+                self.action = match self.fetch_type {
+                    // Anything loaded only needs what changed since
+                    FetchType::All if !self.data.is_empty() => {
+                        DownloaderAction::Resync
+                    }
+                    ft => DownloaderAction::Download(ft),
+                };
             }
         });
     }
 
+    /// Picks a column and text to filter rows by.
+    fn filter_bar(&mut self, ui: &mut egui::Ui) {
+        let column = &T::COLUMNS[self.filter_column];
+        egui::ComboBox::from_id_salt((T::NAME, "filter"))
+            .selected_text(column.header)
+            .show_ui(ui, |ui| {
+                for (i, column) in T::COLUMNS.iter().enumerate() {
+                    ui.selectable_value(
+                        &mut self.filter_column,
+                        i,
+                        column.header,
+                    );
+                }
+            });
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.filter)
+                .hint_text("Filter, or <, > a number")
+                .desired_width(160.0),
+        );
+        if !self.filter.is_empty() && ui.small_button("❌").clicked() {
+            self.filter.clear();
+        }
+    }
+
+    /// Whether an item passes the filter. Text matches anywhere, ignoring
+    /// case; `<`, `<=`, `>` and `>=` compare numbers.
+    fn matches(&self, item: &T) -> bool {
+        let filter = self.filter.trim();
+        if filter.is_empty() {
+            return true;
+        }
+
+        let text = (T::COLUMNS[self.filter_column].text)(item);
+        let comparison = ["<=", ">=", "<", ">"].into_iter().find_map(|op| {
+            let bound = filter.strip_prefix(op)?.trim().parse::<f64>().ok()?;
+            Some((op, bound))
+        });
+
+        if let Some((op, bound)) = comparison {
+            let Ok(value) = text.trim().parse::<f64>() else {
+                return false;
+            };
+            return match op {
+                "<=" => value <= bound,
+                ">=" => value >= bound,
+                "<" => value < bound,
+                _ => value > bound,
+            };
+        }
+
+        text.to_lowercase().contains(&filter.to_lowercase())
+    }
+
+    /// Indices of the items that pass the filter, in order.
+    fn shown(&self) -> Vec<usize> {
+        (0..self.data.len())
+            .filter(|&i| self.matches(&self.data[i]))
+            .collect()
+    }
+
     pub fn table(&mut self, ui: &mut egui::Ui) -> Option<usize> {
-        if self.data.is_empty() {
-            ui.label(format!(
-                "No {}s in memory!\n (Sync button below)",
-                T::NAME,
-            ));
+        let shown = self.shown();
+        if shown.is_empty() {
+            ui.label(if self.data.is_empty() {
+                format!("No {}s in memory!\n (Sync button below)", T::NAME)
+            } else {
+                format!("No {}s match the filter.", T::NAME)
+            });
             return None;
         }
 
+        if self.layout.len() != T::COLUMNS.len() {
+            self.reset_layout();
+        }
+
+        // Positions in `layout` of the columns that are shown
+        let visible = (0..self.layout.len())
+            .filter(|&p| self.layout[p].visible)
+            .collect::<Vec<_>>();
+        let indices = visible
+            .iter()
+            .map(|&p| self.layout[p].index)
+            .collect::<Vec<_>>();
+
         let height = ui.available_height();
-        let table = TableBuilder::new(ui)
+        let mut table = TableBuilder::new(ui)
+            // A new layout gets fresh widths from `layout`
+            .id_salt((T::NAME, &indices))
             .striped(true)
-            .resizable(false)
+            .resizable(true)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-            .column(Column::auto())
-            .columns(Column::remainder(), T::COLUMNS.len() - 2)
-            .column(Column::auto())
             .min_scrolled_height(0.0)
             .max_scroll_height(height)
             .sense(egui::Sense::click());
 
-        let mut selected = None;
+        // Keyboard moves count as selections too
+        let scroll = self.scroll_to.take().filter(|&row| row < shown.len());
+        if let Some(row) = scroll {
+            table = table.scroll_to_row(row, None);
+        }
+        let mut selected = scroll.map(|row| shown[row]);
+
+        for &p in &visible {
+            let column = self.layout[p]
+                .width
+                .map_or_else(Column::remainder, Column::initial);
+            table = table.column(column.at_least(24.0).clip(true));
+        }
+
+        if self.forget_widths {
+            table.reset();
+            self.forget_widths = false;
+        }
+
+        let mut moved = None;
+        let mut changed = false;
+        let mut widths = Vec::new();
+        let mut edit = None;
 
         table
             .header(24.0, |mut header| {
-                T::COLUMNS.iter().enumerate().for_each(|(i, (col, hint))| {
-                    header.col(|ui| {
-                        let sort = format_header(ui, col, hint);
+                for &p in &visible {
+                    let index = self.layout[p].index;
+                    let column = &T::COLUMNS[index];
+
+                    let (_, response) = header.col(|ui| {
+                        let drag_id = egui::Id::new((T::NAME, "header", index));
+                        let sort = format_header(
+                            ui,
+                            drag_id,
+                            ColumnDrag(p),
+                            column.header,
+                            column.hint,
+                        );
 
                         if sort {
-                            self.sort_by = i;
-                            self.data
-                                .par_sort_by(|a, b| a.cmp_by(b, self.sort_by));
+                            self.sort_by = Some(index);
+                            self.keep_selection(|data| {
+                                data.par_sort_by(column.cmp);
+                            });
                         }
                     });
-                });
+
+                    if let Some(from) =
+                        response.dnd_release_payload::<ColumnDrag>()
+                    {
+                        moved = Some((from.0, p));
+                    }
+
+                    response.context_menu(|ui| {
+                        changed |= self.column_menu(ui);
+                    });
+                }
             })
             .body(|mut body| {
-                let mut clicked = None;
-                for (index, item) in self.data.iter().enumerate() {
-                    body.row(18.0, |mut row| {
-                        row.set_selected(self.selected() == Some(index));
+                widths = body.widths().to_vec();
+
+                let (clicked, finished) =
+                    self.rows(&mut body, &indices, &shown);
+                edit = finished;
+
+                if let Some(i) = clicked {
+                    selected = self.select(i);
+                }
+            });
+
+        if let Some(keep) = edit {
+            self.finish_edit(keep);
+        }
 
-                        item.format(&mut row);
-                        // format_pulsar_meta(item, &mut row);
+        if let Some((from, to)) = moved {
+            let column = self.layout.remove(from);
+            self.layout.insert(to, column);
+        } else if !changed {
+            for (&p, w) in visible.iter().zip(widths) {
+                self.layout[p].width = Some(w);
+            }
+        }
 
-                        if row.response().clicked() {
-                            clicked = Some(index);
+        selected
+    }
+
+    /// Adds all rows. Returns the clicked row, if any, and how an inline
+    /// edit ended, if it did.
+    fn rows(
+        &mut self,
+        body: &mut egui_extras::TableBody<'_>,
+        indices: &[usize],
+        shown: &[usize],
+    ) -> (Option<usize>, Option<bool>) {
+        let mut clicked = None;
+        let mut edit = None;
+        let mut followed = None;
+
+        for &index in shown {
+            let item = &self.data[index];
+            body.row(ROW_HEIGHT, |mut row| {
+                row.set_selected(self.selected == Some(index));
+
+                for &i in indices {
+                    let column = &T::COLUMNS[i];
+                    let (_, response) = row.col(|ui| match &mut self.editing {
+                        Some((r, c, text)) if *r == item.id() && *c == i => {
+                            edit = edit_cell(ui, text);
+                        }
+                        _ if T::LINKS.contains(&column.header) => {
+                            if ui.link((column.text)(item)).clicked() {
+                                followed = Some(item.id());
+                            }
+                        }
+                        _ => {
+                            (column.cell)(item, ui);
+                            if self.edited.contains(&(item.id(), i)) {
+                                edited_marker(ui);
+                            }
                         }
                     });
+
+                    if column.edit.is_some() && response.double_clicked() {
+                        self.editing =
+                            Some((item.id(), i, (column.text)(item)));
+                    }
                 }
 
-                selected = clicked.and_then(|i| self.select(i));
+                // The second click of a double-click would deselect
+                let response = row.response();
+                if response.clicked() && !response.double_clicked() {
+                    clicked = Some(index);
+                }
             });
+        }
 
-        selected
+        if let Some(id) = followed {
+            self.action = DownloaderAction::Link(id);
+        }
+
+        (clicked, edit)
+    }
+
+    /// Hands a finished inline edit to the app, or drops it.
+    fn finish_edit(&mut self, keep: bool) {
+        let Some((id, column, value)) = self.editing.take() else {
+            return;
+        };
+
+        if keep {
+            self.action = DownloaderAction::Edit {
+                id,
+                column,
+                value: value.trim().to_string(),
+            };
+        }
+    }
+
+    /// Lets the user toggle columns, from the header context menu. Returns
+    /// whether the layout changed.
+    fn column_menu(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label(RichText::new("Columns").strong());
+        ui.separator();
+
+        let mut changed = false;
+        let shown = self.layout.iter().filter(|c| c.visible).count();
+        for column in &mut self.layout {
+            // Never hide the last column, or there's no header to undo it
+            let enabled = !column.visible || shown > 1;
+            changed |= ui
+                .add_enabled(
+                    enabled,
+                    egui::Checkbox::new(
+                        &mut column.visible,
+                        T::COLUMNS[column.index].header,
+                    ),
+                )
+                .changed();
+        }
+
+        ui.separator();
+        if ui.button("Reset").clicked() {
+            self.reset_layout();
+            self.forget_widths = true;
+            changed = true;
+            ui.close();
+        }
+
+        changed
+    }
+
+    fn reset_layout(&mut self) {
+        self.layout = (0..T::COLUMNS.len()).map(Self::fresh_column).collect();
+    }
+
+    fn fresh_column(index: usize) -> ColumnState {
+        ColumnState {
+            index,
+            visible: !T::HIDDEN.contains(&T::COLUMNS[index].header),
+            width: None,
+        }
+    }
+
+    fn storage_key() -> String {
+        format!("{}-columns", T::NAME)
+    }
+
+    /// Restores the column layout from a previous session, if any.
+    pub fn load_layout(&mut self, storage: &dyn eframe::Storage) {
+        let Some(saved) = eframe::get_value::<Vec<SavedColumn>>(
+            storage,
+            &Self::storage_key(),
+        ) else {
+            return;
+        };
+
+        self.layout = saved
+            .iter()
+            .filter_map(|s| {
+                let index =
+                    T::COLUMNS.iter().position(|c| c.header == s.header)?;
+                Some(ColumnState {
+                    index,
+                    visible: s.visible,
+                    width: s.width,
+                })
+            })
+            .collect();
+
+        // Columns added since the layout was saved go last
+        for index in 0..T::COLUMNS.len() {
+            if !self.layout.iter().any(|c| c.index == index) {
+                self.layout.push(Self::fresh_column(index));
+            }
+        }
+
+        if !self.layout.iter().any(|c| c.visible) {
+            self.reset_layout();
+        }
+    }
+
+    /// Stores the column layout for the next session.
+    pub fn save_layout(&self, storage: &mut dyn eframe::Storage) {
+        if self.layout.is_empty() {
+            return;
+        }
+
+        let saved = self
+            .layout
+            .iter()
+            .map(|c| SavedColumn {
+                header: T::COLUMNS[c.index].header.to_string(),
+                visible: c.visible,
+                width: c.width,
+            })
+            .collect::<Vec<_>>();
+
+        eframe::set_value(storage, &Self::storage_key(), &saved);
     }
 
     pub fn add(&mut self, item: T) {
@@ -184,13 +771,137 @@ where
 
     pub fn set(&mut self, items: Vec<T>) {
         self.data = items;
+        self.fingerprints.clear();
+        self.pending.clear();
+        self.selected = None;
+        self.editing = None;
+        self.fetching = false;
+    }
+
+    /// Drops the rows the database no longer has, and returns the ids of
+    /// those that are new or changed since the last sync.
+    pub fn check(&mut self, fingerprints: Fingerprints) -> Vec<i32> {
+        let present = fingerprints
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<HashSet<_>>();
+        if self.data.iter().any(|item| !present.contains(&item.id())) {
+            self.keep_selection(|data| {
+                data.retain(|item| present.contains(&item.id()));
+            });
+        }
+        if self
+            .editing
+            .as_ref()
+            .is_some_and(|(id, ..)| !present.contains(id))
+        {
+            self.editing = None;
+        }
+        self.fingerprints.retain(|id, _| present.contains(id));
+
+        let loaded = self.data.iter().map(Item::id).collect::<HashSet<_>>();
+        let mut stale = Vec::new();
+        for (id, print) in fingerprints {
+            let fresh = self.fingerprints.get(&id).map_or_else(
+                || loaded.contains(&id),
+                |known| *known == Some(print),
+            );
+            if fresh {
+                self.fingerprints.insert(id, Some(print));
+            } else {
+                self.pending.insert(id, print);
+                stale.push(id);
+            }
+        }
+
+        if stale.is_empty() {
+            self.fetching = false;
+            self.synced += 1;
+        }
+        stale
+    }
+
+    /// Merges the rows fetched again after a resync, keeping the selection
+    /// and sort order.
+    pub fn merge(&mut self, rows: Vec<T>) {
+        let cmp = self.sort_by.map(|i| T::COLUMNS[i].cmp);
+
+        for item in &rows {
+            let print = self.pending.remove(&item.id());
+            self.fingerprints.insert(item.id(), print);
+        }
+
+        self.keep_selection(|data| {
+            for item in rows {
+                match data.iter().position(|i| i.id() == item.id()) {
+                    Some(i) => data[i] = item,
+                    None => data.push(item),
+                }
+            }
+
+            // New rows go where the user's sort puts them, if any, or last
+            if let Some(cmp) = cmp {
+                data.par_sort_by(cmp);
+            }
+        });
+
         self.fetching = false;
+        self.synced += 1;
+    }
+
+    /// How many resyncs have come back, to tell when the data is fresh.
+    pub const fn synced(&self) -> u64 {
+        self.synced
+    }
+
+    /// Runs `f` on the data, then selects the same item as before, if it's
+    /// still there.
+    fn keep_selection(&mut self, f: impl FnOnce(&mut Vec<T>)) {
+        let id = self.selected_id();
+        f(&mut self.data);
+        self.selected =
+            id.and_then(|id| self.data.iter().position(|i| i.id() == id));
+    }
+
+    /// Keeps an accepted inline edit and marks the cell until the
+    /// transaction ends. Rows are found by id, as they may have moved since
+    /// the edit was made.
+    pub fn apply_edit(&mut self, column: usize, item: T) {
+        let id = item.id();
+        let Some(index) = self.data.iter().position(|i| i.id() == id) else {
+            return;
+        };
+        self.data[index] = item;
+        // The row no longer matches what was synced
+        self.fingerprints.insert(id, None);
+        if !self.edited.contains(&(id, column)) {
+            self.edited.push((id, column));
+        }
+    }
+
+    /// Drops the edit markers. Returns whether there were any.
+    pub fn clear_edits(&mut self) -> bool {
+        let any = !self.edited.is_empty();
+        self.edited.clear();
+        any
+    }
+
+    pub fn set_related(&mut self, id: i32, related: T::Related) {
+        self.related = Some((id, Ok(related)));
+    }
+
+    pub fn related_failed(&mut self, id: i32, err: String) {
+        self.related = Some((id, Err(err)));
+    }
+
+    /// Makes sure related records are fetched anew, e.g. after changes.
+    pub fn forget_related(&mut self) {
+        self.related = None;
+        self.related_requested = None;
     }
 
     pub fn action(&mut self) -> DownloaderAction {
-        let a = self.action;
-        self.action = DownloaderAction::None;
-        a
+        std::mem::replace(&mut self.action, DownloaderAction::None)
     }
 
     pub fn select(&mut self, index: usize) -> Option<usize> {
@@ -222,19 +933,51 @@ where
         &self.data
     }
 
+    /// The loaded item with this id, wherever its row is.
+    pub fn get(&self, id: i32) -> Option<&T> {
+        self.data.iter().find(|item| item.id() == id)
+    }
+
     pub fn stop_fetching(&mut self) {
         self.fetching = false;
     }
 }
 
-fn format_header(ui: &mut egui::Ui, text: &str, hint: &str) -> bool {
+/// An inline text field. Returns `Some(true)` to keep the text,
+/// `Some(false)` to discard it, and `None` while still editing.
+fn edit_cell(ui: &mut egui::Ui, text: &mut String) -> Option<bool> {
+    let response = ui.text_edit_singleline(text);
+    if !response.has_focus() && !response.lost_focus() {
+        response.request_focus();
+    }
+
+    response
+        .lost_focus()
+        .then(|| ui.input(|i| i.key_pressed(Key::Enter)))
+}
+
+fn edited_marker(ui: &mut egui::Ui) {
+    ui.label(RichText::new("✏").color(egui::Color32::ORANGE))
+        .on_hover_text("Edited, not committed");
+}
+
+fn format_header(
+    ui: &mut egui::Ui,
+    drag_id: egui::Id,
+    payload: ColumnDrag,
+    text: &str,
+    hint: &str,
+) -> bool {
     ui.set_height(IconicButton::HEIGHTS[0]);
 
-    ui.label(
-        RichText::new(text)
-            .strong()
-            .text_style(egui::TextStyle::Button),
-    )
+    ui.dnd_drag_source(drag_id, payload, |ui| {
+        ui.label(
+            RichText::new(text)
+                .strong()
+                .text_style(egui::TextStyle::Button),
+        )
+    })
+    .response
     .on_hover_text(hint);
 
     ui.add(IconicButton::new("⏷").small().on_hover_text("Sort"))
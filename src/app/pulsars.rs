@@ -1,100 +1,395 @@
-use std::{
-    io::{BufRead, BufReader},
-    path::PathBuf,
-};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
 use arpa::{ARPAError, data_types::PulsarMeta};
 use egui::RichText;
+use log::warn;
 
 use crate::app::{
-    Request, Syncher,
+    Message, Request, Syncher,
+    ephemerides::ParData,
     helpers::{
         ICON_CLEAR, ICON_INSERT, ICON_WRITE, IconicButton, StatusMessage,
         StatusMessageSeverity, confirm_button,
-        downloader::{self, Downloader, DownloaderAction},
+        coordinates::{self, parse_dec, parse_ra},
+        detail_row,
+        downloader::{Downloader, DownloaderAction, Item, ItemColumn},
         enter_data_option, format_data_option, format_unique_data_option,
-        opt_cmp,
+        non_empty, opt_cmp,
     },
 };
+
+mod cone_search;
+mod csv;
+mod duplicates;
+mod import;
+mod merge;
+mod names;
+mod overview;
+mod psrcat;
+mod sky_map;
+use cone_search::ConeSearch;
+use csv::{ColumnMapping, MappingAction, SavedMappings};
+pub use duplicates::{Duplicate, find as find_duplicates};
+use duplicates::{DuplicateAction, DuplicateCheck};
+use import::{ImportAction, ImportPreview, ImportRow};
+use merge::{MergeAction, MergeDialog};
+use names::{NameAction, NameCheck, NameFix};
+pub use overview::TOASummary;
+use overview::{Overview, OverviewAction};
+use sky_map::SkyMap;
+
 const DATA_TYPE: crate::app::DataType = crate::app::DataType::Pulsar;
 
-impl downloader::Item for PulsarMeta {
+/// Where the paths of master ephemerides are kept in egui memory, for the
+/// table to show.
+const MASTER_PATHS_ID: &str = "pulsar-master-paths";
+
+/// Paths by ephemeride id. `None` for ephemerides that don't exist.
+type MasterPaths = Arc<BTreeMap<i32, Option<String>>>;
+
+fn master_path(ctx: &egui::Context, id: i32) -> Option<String> {
+    ctx.data(|d| d.get_temp::<MasterPaths>(egui::Id::new(MASTER_PATHS_ID)))
+        .and_then(|paths| paths.get(&id).cloned().flatten())
+}
+
+/// A read-only column computed from the coordinates, in degrees.
+macro_rules! derived_column {
+    ($header:literal, $hint:literal, $value:expr) => {
+        ItemColumn {
+            header: $header,
+            hint: $hint,
+            cell: |p, ui| {
+                let value: Option<f64> = ($value)(p);
+                let text = value.map(|v| format!("{v:.4}"));
+                ui.label(format_data_option(text.as_ref()));
+            },
+            text: |p| {
+                let value: Option<f64> = ($value)(p);
+                value.map(|v| format!("{v:.4}")).unwrap_or_default()
+            },
+            edit: None,
+            cmp: |a, b| coordinates::cmp_values(($value)(a), ($value)(b)),
+        }
+    };
+}
+
+/// What refers to a pulsar.
+#[derive(Debug)]
+pub struct PulsarRelated {
+    pub ephemerides: Vec<ParData>,
+    pub toa_count: usize,
+}
+
+/// What deleting a pulsar would take with it.
+#[derive(Debug)]
+pub struct PulsarDependents {
+    pub pulsar: PulsarMeta,
+    pub ephemerides: Vec<ParData>,
+    pub toas: Vec<i32>,
+    /// Other pulsars using one of the ephemerides as master. These block
+    /// deletion.
+    pub borrowers: Vec<PulsarMeta>,
+}
+
+impl Item for PulsarMeta {
     const NAME: &str = "pulsar";
-    const COLUMNS: &[(&str, &str)] = &[
-        ("ID", "The automatically generated ID."),
-        (
-            "Alias",
-            "An alias for the pulsar, often the same as the J name",
-        ),
-        ("J name", "Optional."),
-        ("B name", "Optional."),
-        ("RA", "J2000 right ascension, optional."),
-        ("DEC", "J2000 declination, optional."),
-        (".par id", "Master ephemeride file id"),
+    const COLUMNS: &[ItemColumn<Self>] = &[
+        ItemColumn {
+            header: "ID",
+            hint: "The automatically generated ID.",
+            cell: |p, ui| {
+                ui.label(p.id.to_string());
+            },
+            text: |p| p.id.to_string(),
+            edit: None,
+            cmp: |a, b| a.id.cmp(&b.id),
+        },
+        ItemColumn {
+            header: "Alias",
+            hint: "An alias for the pulsar, often the same as the J name",
+            cell: |p, ui| {
+                ui.label(RichText::new(&p.alias).strong());
+            },
+            text: |p| p.alias.clone(),
+            edit: Some(|p, v| p.alias = v),
+            cmp: |a, b| a.alias.cmp(&b.alias),
+        },
+        ItemColumn {
+            header: "J name",
+            hint: "Optional.",
+            cell: |p, ui| {
+                ui.label(format_unique_data_option(
+                    p.j_name.as_ref(),
+                    &p.alias,
+                ));
+            },
+            text: |p| p.j_name.clone().unwrap_or_default(),
+            edit: Some(|p, v| p.j_name = non_empty(&v)),
+            cmp: |a, b| opt_cmp(a.j_name.as_ref(), b.j_name.as_ref()),
+        },
+        ItemColumn {
+            header: "B name",
+            hint: "Optional.",
+            cell: |p, ui| {
+                ui.label(format_unique_data_option(
+                    p.b_name.as_ref(),
+                    &p.alias,
+                ));
+            },
+            text: |p| p.b_name.clone().unwrap_or_default(),
+            edit: Some(|p, v| p.b_name = non_empty(&v)),
+            cmp: |a, b| opt_cmp(a.b_name.as_ref(), b.b_name.as_ref()),
+        },
+        ItemColumn {
+            header: "RA",
+            hint: "J2000 right ascension, optional.",
+            cell: |p, ui| {
+                let ra = p.j2000_ra.as_ref();
+                let ra =
+                    ra.map(|t| coordinates::display(ui.ctx(), t, parse_ra));
+                ui.label(format_data_option(ra.as_ref()));
+            },
+            text: |p| p.j2000_ra.clone().unwrap_or_default(),
+            edit: Some(|p, v| p.j2000_ra = non_empty(&v)),
+            cmp: |a, b| {
+                coordinates::cmp(
+                    a.j2000_ra.as_ref(),
+                    b.j2000_ra.as_ref(),
+                    parse_ra,
+                )
+            },
+        },
+        ItemColumn {
+            header: "DEC",
+            hint: "J2000 declination, optional.",
+            cell: |p, ui| {
+                let dec = p.j2000_dec.as_ref();
+                let dec =
+                    dec.map(|t| coordinates::display(ui.ctx(), t, parse_dec));
+                ui.label(format_data_option(dec.as_ref()));
+            },
+            text: |p| p.j2000_dec.clone().unwrap_or_default(),
+            edit: Some(|p, v| p.j2000_dec = non_empty(&v)),
+            cmp: |a, b| {
+                coordinates::cmp(
+                    a.j2000_dec.as_ref(),
+                    b.j2000_dec.as_ref(),
+                    parse_dec,
+                )
+            },
+        },
+        derived_column!("l", "Galactic longitude, from RA/DEC.", |p| {
+            galactic(p).map(|(l, _)| l)
+        }),
+        derived_column!("b", "Galactic latitude, from RA/DEC.", |p| {
+            galactic(p).map(|(_, b)| b)
+        }),
+        derived_column!("Ecl. lon", "Ecliptic longitude, from RA/DEC.", |p| {
+            ecliptic(p).map(|(lon, _)| lon)
+        }),
+        derived_column!("Ecl. lat", "Ecliptic latitude, from RA/DEC.", |p| {
+            ecliptic(p).map(|(_, lat)| lat)
+        }),
+        ItemColumn {
+            header: "Names",
+            hint: "Whether the J and B names agree with the coordinates.",
+            cell: |p, ui| match (names::check(p).j_name, names::check_b_name(p))
+            {
+                (Some(expected), _) => {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                        .on_hover_text(format!("Expected {expected}"));
+                }
+                (None, Some(expected)) => {
+                    ui.label("ℹ").on_hover_text(format!(
+                        "The B name is roughly {expected}"
+                    ));
+                }
+                (None, None) => {
+                    ui.label("✔");
+                }
+            },
+            text: |p| {
+                if names::check(p).is_empty() {
+                    "ok"
+                } else {
+                    "mismatch"
+                }
+                .into()
+            },
+            edit: None,
+            cmp: |a, b| {
+                names::check(b).is_empty().cmp(&names::check(a).is_empty())
+            },
+        },
+        ItemColumn {
+            header: ".par id",
+            hint: "Master ephemeride file id",
+            cell: |p, ui| {
+                ui.label(format_data_option(p.master_parfile_id.as_ref()));
+            },
+            text: |p| {
+                p.master_parfile_id
+                    .map(|i| i.to_string())
+                    .unwrap_or_default()
+            },
+            edit: None,
+            cmp: |a, b| {
+                opt_cmp(
+                    a.master_parfile_id.as_ref(),
+                    b.master_parfile_id.as_ref(),
+                )
+            },
+        },
+        ItemColumn {
+            header: "Master .par",
+            hint: "Master ephemeride file path. Filters and sorts by id.",
+            cell: |p, ui| {
+                let path = p.master_parfile_id.and_then(|m| {
+                    master_path(ui.ctx(), m).or_else(|| Some(format!("#{m}")))
+                });
+                ui.label(format_data_option(path.as_ref()));
+            },
+            text: |p| {
+                p.master_parfile_id
+                    .map(|i| i.to_string())
+                    .unwrap_or_default()
+            },
+            edit: None,
+            cmp: |a, b| {
+                opt_cmp(
+                    a.master_parfile_id.as_ref(),
+                    b.master_parfile_id.as_ref(),
+                )
+            },
+        },
     ];
 
+    const HIDDEN: &[&str] = &["l", "b", "Ecl. lon", "Ecl. lat"];
+
+    type Related = PulsarRelated;
+
     fn id(&self) -> i32 {
         self.id
     }
 
-    fn format(&self, row: &mut egui_extras::TableRow) {
-        // id
-        row.col(|ui| {
-            ui.label(self.id.to_string());
-        });
-
-        // names
-        row.col(|ui| {
-            ui.label(RichText::new(&self.alias).strong());
-        });
-        row.col(|ui| {
-            ui.label(format_unique_data_option(
-                self.j_name.as_ref(),
-                &self.alias,
-            ));
-        });
-        row.col(|ui| {
-            ui.label(format_unique_data_option(
-                self.b_name.as_ref(),
-                &self.alias,
-            ));
-        });
-        // coords
-        row.col(|ui| {
-            ui.label(format_data_option(self.j2000_ra.as_ref()));
-        });
-        row.col(|ui| {
-            ui.label(format_data_option(self.j2000_dec.as_ref()));
-        });
-        // par
-        row.col(|ui| {
-            ui.label(format_data_option(self.master_parfile_id.as_ref()));
+    fn details(&self, ui: &mut egui::Ui) {
+        detail_row(ui, "ID", self.id.to_string());
+        detail_row(ui, "Alias", RichText::new(&self.alias).strong());
+        detail_row(ui, "J name", format_data_option(self.j_name.as_ref()));
+        detail_row(ui, "B name", format_data_option(self.b_name.as_ref()));
+        detail_row(ui, "RA", format_data_option(self.j2000_ra.as_ref()));
+        detail_row(ui, "DEC", format_data_option(self.j2000_dec.as_ref()));
+        if let (Some((l, b)), Some((lon, lat))) =
+            (galactic(self), ecliptic(self))
+        {
+            detail_row(ui, "Galactic", format!("l {l:.4}°, b {b:.4}°"));
+            detail_row(ui, "Ecliptic", format!("{lon:.4}°, {lat:.4}°"));
+        }
+        let master = self.master_parfile_id.map(|m| {
+            master_path(ui.ctx(), m)
+                .map_or_else(|| m.to_string(), |path| format!("#{m} {path}"))
         });
+        detail_row(ui, "Master .par", format_data_option(master.as_ref()));
     }
 
-    fn cmp_by(&self, other: &Self, index: usize) -> std::cmp::Ordering {
-        match index {
-            0 => self.id.cmp(&other.id),
-            1 => self.alias.cmp(&other.alias),
-            2 => opt_cmp(self.j_name.as_ref(), other.j_name.as_ref()),
-            3 => opt_cmp(self.b_name.as_ref(), other.b_name.as_ref()),
-            4 => opt_cmp(self.j2000_ra.as_ref(), other.j2000_ra.as_ref()),
-            5 => opt_cmp(self.j2000_dec.as_ref(), other.j2000_dec.as_ref()),
-            6 => opt_cmp(
-                self.master_parfile_id.as_ref(),
-                other.master_parfile_id.as_ref(),
-            ),
-            _ => std::cmp::Ordering::Equal,
+    fn related(&self, related: &Self::Related, ui: &mut egui::Ui) {
+        ui.label(format!("{} TOA(s)", related.toa_count));
+        ui.add_space(8.0);
+
+        if related.ephemerides.is_empty() {
+            ui.label(RichText::new("No ephemerides").italics());
+        }
+        for par in &related.ephemerides {
+            let text = format!("#{} {}", par.id, par.path);
+            if self.master_parfile_id == Some(par.id) {
+                ui.label(RichText::new(text).strong())
+                    .on_hover_text("Master ephemeride");
+            } else {
+                ui.label(text);
+            }
         }
     }
 }
 
+/// RA and DEC in degrees, if both are there and make sense.
+fn equatorial(p: &PulsarMeta) -> Option<(f64, f64)> {
+    let ra = parse_ra(p.j2000_ra.as_ref()?).ok()?;
+    let dec = parse_dec(p.j2000_dec.as_ref()?).ok()?;
+    Some((ra, dec))
+}
+
+fn galactic(p: &PulsarMeta) -> Option<(f64, f64)> {
+    equatorial(p).map(|(ra, dec)| coordinates::galactic(ra, dec))
+}
+
+fn ecliptic(p: &PulsarMeta) -> Option<(f64, f64)> {
+    equatorial(p).map(|(ra, dec)| coordinates::ecliptic(ra, dec))
+}
+
+/// Checks and normalizes a pulsar's coordinates, then verifies it.
+pub fn validate(meta: &mut PulsarMeta) -> Result<(), ARPAError> {
+    // `verify` only takes full `hh:mm:ss` coordinates, so degrees and
+    // shorter forms are rewritten first
+    if let Some(ra) = &meta.j2000_ra {
+        meta.j2000_ra = Some(coordinates::normalize_ra(ra)?);
+    }
+    if let Some(dec) = &meta.j2000_dec {
+        meta.j2000_dec = Some(coordinates::normalize_dec(dec)?);
+    }
+
+    meta.verify()?;
+    Ok(())
+}
+
+/// A pulsar with just its names and position, as files give them. Without an
+/// alias, the J or B name stands in; without any name, there is no pulsar.
+pub fn make_pulsar(
+    alias: Option<String>,
+    j_name: Option<String>,
+    b_name: Option<String>,
+    ra: Option<String>,
+    dec: Option<String>,
+) -> Option<PulsarMeta> {
+    let mut meta = PulsarMeta::null();
+    meta.alias = alias.or_else(|| j_name.clone().or_else(|| b_name.clone()))?;
+    meta.j_name = j_name;
+    meta.b_name = b_name;
+    meta.j2000_ra = ra;
+    meta.j2000_dec = dec;
+    Some(meta)
+}
+
 pub struct PulsarsApp {
     messages: Vec<StatusMessage>,
     pub downloader: Downloader<PulsarMeta>,
 
     new_pulsar: PulsarMeta,
     pulsar_file: Option<PathBuf>,
+
+    show_map: bool,
+    sky_map: SkyMap,
+    cone_search: ConeSearch,
+    name_check: NameCheck,
+    merge: MergeDialog,
+    overview: Overview,
+    move_to_par_id: Option<i32>,
+
+    /// The selected pulsar's ephemerides, `None` inside while fetching.
+    pars: Option<(i32, Option<Vec<ParData>>)>,
+    master_paths: BTreeMap<i32, Option<String>>,
+    /// Whether the table has yet to see the latest paths.
+    master_paths_changed: bool,
+    master_paths_pending: bool,
+
+    /// Awaiting confirmation for a delete.
+    dependents: Option<PulsarDependents>,
+    /// A new pulsar that looks like one we have.
+    duplicate_check: Option<DuplicateCheck>,
+    /// A file being looked over before import.
+    import: Option<ImportPreview>,
+    /// A CSV/TSV file waiting for its columns to be mapped.
+    mapping: Option<ColumnMapping>,
+    csv_mappings: SavedMappings,
 }
 
 impl PulsarsApp {
@@ -105,18 +400,37 @@ impl PulsarsApp {
 
             new_pulsar: PulsarMeta::null(),
             pulsar_file: None,
+
+            show_map: false,
+            sky_map: SkyMap::new(),
+            cone_search: ConeSearch::new(),
+            name_check: NameCheck::new(),
+            merge: MergeDialog::new(),
+            overview: Overview::new(),
+            move_to_par_id: None,
+
+            pars: None,
+            master_paths: BTreeMap::new(),
+            master_paths_changed: false,
+            master_paths_pending: false,
+
+            dependents: None,
+            duplicate_check: None,
+            import: None,
+            mapping: None,
+            csv_mappings: SavedMappings::new(),
         }
     }
 
     pub fn show(&mut self, ctx: &egui::Context, archivist: &Syncher) {
         self.downloader.action_bar(ctx);
+        self.downloader.detail_panel(ctx);
 
         match self.downloader.action() {
-            DownloaderAction::None => {}
+            DownloaderAction::None | DownloaderAction::Link(_) => {}
             DownloaderAction::Delete(index) => match index {
-                Some(id) => {
-                    archivist.request(Request::DeleteItem(DATA_TYPE, id));
-                }
+                // Look before we leap
+                Some(id) => archivist.request(Request::PulsarDependents(id)),
 
                 None => {
                     self.messages.push(StatusMessage {
@@ -130,6 +444,62 @@ impl PulsarsApp {
                 let request = Request::Download(DATA_TYPE, ft);
                 archivist.request(request);
             }
+
+            DownloaderAction::Related(id) => {
+                archivist.request(Request::Related(DATA_TYPE, id));
+            }
+
+            DownloaderAction::Resync => {
+                archivist.request(Request::Resync(DATA_TYPE));
+            }
+
+            DownloaderAction::Edit { id, column, value } => {
+                self.edit_cell(archivist, id, column, value);
+            }
+        }
+
+        self.dependents_modal(ctx, archivist);
+        self.duplicates_modal(ctx, archivist);
+
+        let data = self.downloader.data();
+        let selected = self.downloader.selected().map(|i| &data[i]);
+        if let Some(id) = self.cone_search.show(ctx, data, selected) {
+            self.select_with_id(id);
+        }
+
+        for action in self.name_check.show(ctx, self.downloader.data()) {
+            match action {
+                NameAction::Select(id) => {
+                    self.select_with_id(id);
+                }
+                NameAction::Fix(id, fix) => self.fix_names(archivist, id, &fix),
+            }
+        }
+
+        self.fetch_pars(archivist);
+        self.fetch_master_paths(ctx, archivist);
+
+        let data = self.downloader.data();
+        let selected = self.downloader.selected().map(|i| &data[i]);
+        let pars = self.pars.as_ref().and_then(|(_, pars)| pars.as_deref());
+        match self.overview.show(ctx, selected, pars) {
+            OverviewAction::None => {}
+            OverviewAction::Fetch(id) => {
+                archivist.request(Request::PulsarTOAs(id));
+            }
+            OverviewAction::OpenEphemeride(id) => {
+                self.move_to_par_id = Some(id);
+            }
+        }
+
+        match self.merge.show(ctx, self.downloader.data()) {
+            MergeAction::None | MergeAction::Cancel => {}
+            MergeAction::Preview(a, b) => {
+                archivist.request(Request::MergePreview(a, b));
+            }
+            MergeAction::Merge { keep, lose, pulsar } => {
+                archivist.request(Request::MergePulsars { keep, lose, pulsar });
+            }
         }
 
         let response = egui::CentralPanel::default()
@@ -156,33 +526,129 @@ impl PulsarsApp {
         });
 
         // Handle input file
-        if let Some(path) = self.pulsar_file.take() {
-            let results = match Self::read_pulsars_from_file(path) {
-                Ok(rs) => rs,
-                Err(err) => {
-                    self.messages.push(StatusMessage {
-                        severity: StatusMessageSeverity::Error,
-                        message: err.to_string(),
-                    });
-                    return;
-                }
-            };
+        if let Some(path) = self.pulsar_file.take()
+            && let Err(err) = self.read_pulsars_from_file(path, archivist)
+        {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Error,
+                message: err.to_string(),
+            });
+        }
 
-            for result in results {
-                match result {
-                    Ok(meta) => archivist.request(Request::AddPulsar(meta)),
+        self.mapping_modal(ctx, archivist);
+        self.import_modal(ctx, archivist);
+    }
 
-                    Err(err) => self.messages.push(StatusMessage {
-                        severity: StatusMessageSeverity::Error,
-                        message: err.to_string(),
-                    }),
+    pub fn reset_ui(&mut self) {
+        self.downloader.stop_fetching();
+        self.master_paths_pending = false;
+    }
+
+    /// Fetches the selected pulsar's ephemerides when the selection changes.
+    fn fetch_pars(&mut self, archivist: &Syncher) {
+        let selected = self.downloader.selected_id();
+        if selected == self.pars.as_ref().map(|(id, _)| *id) {
+            return;
+        }
+
+        self.pars = selected.map(|id| {
+            archivist.request(Request::PulsarEphemerides(id));
+            (id, None)
+        });
+    }
+
+    fn selected_pars(&self) -> Option<&[ParData]> {
+        self.pars.as_ref().and_then(|(_, pars)| pars.as_deref())
+    }
+
+    /// Fetches the paths of master ephemerides the table doesn't know, and
+    /// hands what it knows to the table.
+    fn fetch_master_paths(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        if self.master_paths_changed {
+            let paths: MasterPaths = Arc::new(self.master_paths.clone());
+            let id = egui::Id::new(MASTER_PATHS_ID);
+            ctx.data_mut(|d| d.insert_temp(id, paths));
+            self.master_paths_changed = false;
+        }
+
+        if self.master_paths_pending {
+            return;
+        }
+
+        let mut missing = self
+            .downloader
+            .data()
+            .iter()
+            .filter_map(|p| p.master_parfile_id)
+            .filter(|m| !self.master_paths.contains_key(m))
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing.dedup();
+
+        if !missing.is_empty() {
+            archivist.request(Request::ParPaths(missing));
+            self.master_paths_pending = true;
+        }
+    }
+
+    /// Takes the messages that only concern this tab.
+    pub fn handle_message(&mut self, message: Message, archivist: &Syncher) {
+        match message {
+            Message::PulsarDependents(dependents) => {
+                self.dependents = Some(dependents);
+            }
+            Message::PulsarDuplicates(pulsar, duplicates) => {
+                self.check_duplicates(pulsar, duplicates, archivist);
+            }
+            Message::MergePreview(sides) => self.merge.set_preview(*sides),
+            Message::PulsarEphemerides(id, ephemerides) => {
+                if let Some((selected, pars)) = &mut self.pars
+                    && *selected == id
+                {
+                    *pars = Some(ephemerides);
                 }
             }
+            Message::ParPaths(paths) => {
+                self.master_paths.extend(paths);
+                self.master_paths_changed = true;
+                self.master_paths_pending = false;
+            }
+            Message::PulsarTOAs(id, toas) => self.overview.set_toas(id, toas),
+            _ => warn!("Pulsars can't handle {message:?}"),
         }
     }
 
-    pub fn reset_ui(&mut self) {
-        self.downloader.stop_fetching();
+    /// Adds a checked pulsar, unless it looks like one we have, in which
+    /// case the user gets to choose.
+    fn check_duplicates(
+        &mut self,
+        pulsar: PulsarMeta,
+        duplicates: Vec<Duplicate>,
+        archivist: &Syncher,
+    ) {
+        if duplicates.is_empty() {
+            archivist.request(Request::AddPulsar(pulsar));
+        } else {
+            self.duplicate_check = Some(DuplicateCheck { pulsar, duplicates });
+        }
+    }
+
+    /// Whether a new pulsar looks like one we have, and waits for the user.
+    pub const fn checking_duplicates(&self) -> bool {
+        self.duplicate_check.is_some()
+    }
+
+    /// Something changed, so what was fetched for the selection and the
+    /// master column is fetched again.
+    pub fn forget_fetched(&mut self) {
+        self.overview.forget();
+        self.pars = None;
+        self.master_paths.clear();
+        self.master_paths_changed = true;
+    }
+
+    pub(crate) const fn select_ephemeride(&mut self) -> Option<i32> {
+        self.move_to_par_id.take()
     }
 
     pub fn messages(&mut self) -> &mut Vec<StatusMessage> {
@@ -199,7 +665,7 @@ impl PulsarsApp {
 
         ui.horizontal(|ui| {
             ui.add_space(16.0);
-            self.pulsar_data_entry(ui);
+            self.pulsar_data_entry(ui, archivist);
             ui.add_space(8.0);
             ui.separator();
             ui.add_space(8.0);
@@ -211,6 +677,40 @@ impl PulsarsApp {
         });
 
         ui.separator();
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.show_map, false, "Table");
+            ui.selectable_value(&mut self.show_map, true, "Sky map");
+            ui.separator();
+            ui.toggle_value(&mut self.cone_search.open, "Cone search");
+            ui.toggle_value(&mut self.name_check.open, "Name check");
+            ui.toggle_value(&mut self.overview.open, "Overview");
+            if ui.selectable_label(self.merge.open, "Merge").clicked() {
+                if self.merge.open {
+                    self.merge.open = false;
+                } else {
+                    self.merge.start(self.downloader.selected_id());
+                }
+            }
+            ui.separator();
+
+            let mut degrees = coordinates::in_degrees(ui.ctx());
+            if ui
+                .checkbox(&mut degrees, "Coordinates in degrees")
+                .changed()
+            {
+                coordinates::set_in_degrees(ui.ctx(), degrees);
+            }
+        });
+
+        if self.show_map {
+            let data = self.downloader.data();
+            let selected = self.downloader.selected_id();
+            if let Some(id) = self.sky_map.show(ui, data, selected) {
+                self.select_with_id(id);
+            }
+            return;
+        }
+
         // self.pulsar_table(ui);
         let selected = self.downloader.table(ui);
         if let Some(i) = selected {
@@ -218,7 +718,7 @@ impl PulsarsApp {
         }
     }
 
-    fn pulsar_data_entry(&mut self, ui: &mut egui::Ui) {
+    fn pulsar_data_entry(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
         egui::Grid::new("new_pulsar_grid")
             .num_columns(2)
             .spacing([32.0, 4.0])
@@ -243,9 +743,78 @@ impl PulsarsApp {
                 ui.label("DEC");
                 enter_data_option(ui, &mut self.new_pulsar.j2000_dec);
                 ui.end_row();
+
+                ui.label("Master").on_hover_text(
+                    "The selected pulsar's master ephemeride. Changes are \
+                    sent right away.",
+                );
+                self.master_choice(ui, archivist);
+                ui.end_row();
             });
     }
 
+    /// A drop-down of the selected pulsar's ephemerides.
+    fn master_choice(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
+        let Some(id) = self.downloader.selected_id() else {
+            ui.label(RichText::new("Select a pulsar").italics());
+            return;
+        };
+        let Some(pars) = self.selected_pars() else {
+            ui.spinner();
+            return;
+        };
+
+        let current = self.downloader.get(id).and_then(|p| p.master_parfile_id);
+        let label = |par: &ParData| format!("#{} {}", par.id, par.path);
+        let text = current.map_or_else(
+            || "(none)".to_string(),
+            |m| {
+                pars.iter()
+                    .find(|p| p.id == m)
+                    .map_or_else(|| format!("#{m}"), label)
+            },
+        );
+
+        let mut chosen = current;
+        egui::ComboBox::from_id_salt("pulsar_master")
+            .selected_text(text)
+            .width(ui.spacing().text_edit_width)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut chosen, None, "(none)");
+                for par in pars {
+                    ui.selectable_value(&mut chosen, Some(par.id), label(par));
+                }
+            });
+
+        if chosen != current {
+            self.set_master(archivist, id, chosen);
+        }
+    }
+
+    /// Sets the master ephemeride of the pulsar with this id, like an inline
+    /// edit.
+    fn set_master(
+        &mut self,
+        archivist: &Syncher,
+        id: i32,
+        master: Option<i32>,
+    ) {
+        let Some(mut meta) = self.downloader.get(id).cloned() else {
+            return;
+        };
+        archivist.request(Request::SetMaster(meta.id, master));
+
+        meta.master_parfile_id = master;
+        self.new_pulsar.master_parfile_id = master;
+        for header in [".par id", "Master .par"] {
+            let column =
+                PulsarMeta::COLUMNS.iter().position(|c| c.header == header);
+            if let Some(column) = column {
+                self.downloader.apply_edit(column, meta.clone());
+            }
+        }
+    }
+
     fn pulsar_data_controls(&mut self, ui: &mut egui::Ui, archivist: &Syncher) {
         ui.vertical(|ui| {
             ui.add_space(6.0);
@@ -269,7 +838,7 @@ impl PulsarsApp {
             }
 
             if new.clicked() {
-                if let Err(err) = self.new_pulsar.verify() {
+                if let Err(err) = validate(&mut self.new_pulsar) {
                     self.messages.push(StatusMessage {
                         severity: StatusMessageSeverity::Error,
                         message: format!("Cannot add pulsar! {err}"),
@@ -277,12 +846,14 @@ impl PulsarsApp {
                     return;
                 }
 
-                let meta = self.new_pulsar.clone();
-                archivist.request(Request::AddPulsar(meta));
+                // Added once it's clear it isn't a duplicate
+                let mut meta = self.new_pulsar.clone();
+                names::fill_j_name(&mut meta);
+                archivist.request(Request::CheckPulsar(meta));
             }
 
             if confirm_button(&overwrite, "Overwrite selected?") {
-                if let Err(err) = self.new_pulsar.verify() {
+                if let Err(err) = validate(&mut self.new_pulsar) {
                     self.messages.push(StatusMessage {
                         severity: StatusMessageSeverity::Error,
                         message: format!("Cannot overwrite pulsar! {err}"),
@@ -300,45 +871,404 @@ impl PulsarsApp {
         });
     }
 
+    /// Lists what a delete would cascade to, and asks to go ahead.
+    fn dependents_modal(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        let Some(deps) = &self.dependents else {
+            return;
+        };
+
+        let mut close = false;
+        let modal = egui::Modal::new(egui::Id::new("pulsar_dependents"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading(format!("Delete {}?", deps.pulsar.alias));
+            ui.add_space(8.0);
+
+            if deps.ephemerides.is_empty() && deps.toas.is_empty() {
+                ui.label("Nothing else refers to this pulsar.");
+            } else {
+                ui.label("These will be deleted along with it:");
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for par in &deps.ephemerides {
+                            let master =
+                                deps.pulsar.master_parfile_id == Some(par.id);
+                            ui.label(format!(
+                                "Ephemeride #{}{}: {}",
+                                par.id,
+                                if master { " (master)" } else { "" },
+                                par.path,
+                            ));
+                        }
+                        if !deps.toas.is_empty() {
+                            ui.label(format!("{} TOAs", deps.toas.len()));
+                        }
+                    });
+            }
+
+            if !deps.borrowers.is_empty() {
+                ui.add_space(8.0);
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "Cannot delete: other pulsars use these ephemerides as \
+                    master:",
+                );
+                for other in &deps.borrowers {
+                    ui.label(format!("#{} {}", other.id, other.alias));
+                }
+            }
+
+            ui.add_space(8.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                let delete = ui.add_enabled(
+                    deps.borrowers.is_empty(),
+                    egui::Button::new("Delete with dependents"),
+                );
+                if delete.clicked() {
+                    archivist.request(Request::DeletePulsar(deps.pulsar.id));
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+        if close || response.should_close() {
+            self.dependents = None;
+        }
+    }
+
+    /// Asks what to do with a new pulsar that looks like one we have.
+    fn duplicates_modal(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        let Some(check) = &self.duplicate_check else {
+            return;
+        };
+
+        match check.show(ctx) {
+            DuplicateAction::None => return,
+            DuplicateAction::Cancel => {}
+            DuplicateAction::AddAnyway => {
+                archivist.request(Request::AddPulsar(check.pulsar.clone()));
+            }
+            DuplicateAction::Merge(id) => {
+                let existing =
+                    check.duplicates.iter().find(|d| d.pulsar.id == id);
+                if let Some(existing) = existing {
+                    let merged =
+                        duplicates::merge(&existing.pulsar, &check.pulsar);
+                    self.merge_into(archivist, &[(id, merged)]);
+                }
+            }
+        }
+        self.duplicate_check = None;
+    }
+
+    /// Overwrites existing pulsars with merged records, then fetches them.
+    fn merge_into(
+        &mut self,
+        archivist: &Syncher,
+        merged: &[(i32, PulsarMeta)],
+    ) {
+        for (id, meta) in merged {
+            let mut meta = meta.clone();
+            if let Err(err) = validate(&mut meta) {
+                self.messages.push(StatusMessage {
+                    severity: StatusMessageSeverity::Error,
+                    message: format!("Cannot merge into #{id}! {err}"),
+                });
+                continue;
+            }
+            archivist.request(Request::UpdatePulsar(*id, meta));
+        }
+
+        archivist.request(Request::Resync(DATA_TYPE));
+    }
+
+    /// Sets the names a pulsar's coordinates call for, like an inline edit.
+    fn fix_names(&mut self, archivist: &Syncher, id: i32, fix: &NameFix) {
+        let Some(mut meta) = self.downloader.get(id).cloned() else {
+            self.messages.push(StatusMessage::wrong());
+            return;
+        };
+        fix.apply(&mut meta);
+
+        if let Err(err) = validate(&mut meta) {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Error,
+                message: format!("Cannot fix {}! {err}", meta.alias),
+            });
+            return;
+        }
+
+        archivist.request(Request::UpdatePulsar(id, meta.clone()));
+
+        // Verifying clears the id
+        meta.id = id;
+        let column = PulsarMeta::COLUMNS
+            .iter()
+            .position(|c| c.header == "J name");
+        if let (Some(_), Some(column)) = (&fix.j_name, column) {
+            self.downloader.apply_edit(column, meta);
+        }
+    }
+
+    /// Verifies an inline edit and sends it off as an update.
+    fn edit_cell(
+        &mut self,
+        archivist: &Syncher,
+        id: i32,
+        column: usize,
+        value: String,
+    ) {
+        let (Some(original), Some(edit)) =
+            (self.downloader.get(id), PulsarMeta::COLUMNS[column].edit)
+        else {
+            self.messages.push(StatusMessage::wrong());
+            return;
+        };
+
+        let mut meta = original.clone();
+        edit(&mut meta, value);
+
+        if let Err(err) = validate(&mut meta) {
+            self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Error,
+                message: format!("Cannot edit pulsar! {err}"),
+            });
+            return;
+        }
+
+        archivist.request(Request::UpdatePulsar(id, meta.clone()));
+
+        // Verifying clears the id
+        meta.id = id;
+        self.downloader.apply_edit(column, meta);
+    }
+
     fn pulsar_file_button(&mut self, ui: &mut egui::Ui) {
-        let load = ui.add_sized(
-            [ui.available_width() - 16.0, ui.available_height()],
-            egui::Button::new("Load file"),
-        );
+        ui.vertical(|ui| {
+            let size = [ui.available_width() - 16.0, 0.0];
+            let load = ui.add_sized(size, egui::Button::new("Load file"));
+            let export = ui.add_sized(size, egui::Button::new("Export"));
+
+            if load.clicked() {
+                self.pulsar_file = rfd::FileDialog::new().pick_file();
+            }
+
+            egui::Popup::menu(&export).show(|ui| {
+                let all = self.downloader.data().len();
+                let selected = self.downloader.selected();
+
+                if ui
+                    .add_enabled(
+                        all > 0,
+                        egui::Button::new(format!("All {all}")),
+                    )
+                    .clicked()
+                {
+                    self.export_pulsars(None);
+                }
+                if ui
+                    .add_enabled(
+                        selected.is_some(),
+                        egui::Button::new("Selected"),
+                    )
+                    .clicked()
+                {
+                    self.export_pulsars(selected);
+                }
+            });
+        });
+    }
+
+    /// Writes loaded pulsars, or just the one at `index`, to a file of
+    /// choice.
+    fn export_pulsars(&mut self, index: Option<usize>) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("pulsars.txt")
+            .save_file()
+        else {
+            return;
+        };
 
-        if load.clicked() {
-            self.pulsar_file = rfd::FileDialog::new().pick_file();
+        let data = self.downloader.data();
+        let metas = index.map_or(data, |i| &data[i..=i]);
+
+        match std::fs::write(&path, Self::format_pulsars(metas)) {
+            Ok(()) => self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Info,
+                message: format!(
+                    "Exported {} pulsars to {}",
+                    metas.len(),
+                    path.display()
+                ),
+            }),
+            Err(err) => self.messages.push(StatusMessage {
+                severity: StatusMessageSeverity::Error,
+                message: format!("Cannot export pulsars! {err}"),
+            }),
         }
     }
 
+    /// Lays out pulsars the way `PulsarMeta::from_strs` reads them, with `.`
+    /// for missing values.
+    fn format_pulsars(metas: &[PulsarMeta]) -> String {
+        let rows = metas
+            .iter()
+            .map(|p| {
+                [
+                    Some(&p.alias),
+                    p.j_name.as_ref(),
+                    p.b_name.as_ref(),
+                    p.j2000_ra.as_ref(),
+                    p.j2000_dec.as_ref(),
+                ]
+                .map(|v| v.map_or(".", String::as_str))
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["# alias", "j_name", "b_name", "j2000_ra", "j2000_dec"];
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (w, v) in widths.iter_mut().zip(row) {
+                *w = (*w).max(v.len());
+            }
+        }
+
+        let line = |fields: [&str; 5]| {
+            let padded = fields
+                .iter()
+                .zip(widths)
+                .map(|(v, w)| format!("{v:<w$}"))
+                .collect::<Vec<_>>();
+            padded.join("  ").trim_end().to_string() + "\n"
+        };
+
+        let mut text = line(header);
+        for row in rows {
+            text += &line(row);
+        }
+        text
+    }
+
+    /// Reads a pulsar list, a psrcat database or a `psrcat -c` table into
+    /// the import preview. CSV/TSV files get their columns mapped first.
     fn read_pulsars_from_file(
+        &mut self,
         path: PathBuf,
-    ) -> Result<Vec<Result<PulsarMeta, ARPAError>>, ARPAError> {
-        let reader = BufReader::new(std::fs::File::open(path)?);
-        let results = reader
-            .lines()
-            .map_while(std::result::Result::ok)
-            .map(|l| {
-                l.split_whitespace().map(str::to_string).collect::<Vec<_>>()
-            })
-            .filter(|l| l.first().is_some_and(|w| !w.starts_with('#')))
-            .map(|ws| {
-                PulsarMeta::from_strs(
-                    &ws.iter().map(String::as_str).collect::<Vec<_>>(),
-                )
-            })
-            .collect();
+        archivist: &Syncher,
+    ) -> Result<(), ARPAError> {
+        let text = std::fs::read_to_string(&path)?;
+
+        if let Some(delimiter) = csv::delimiter(&path, &text) {
+            let mapping =
+                ColumnMapping::new(path, &text, delimiter, &self.csv_mappings)?;
+            self.mapping = Some(mapping);
+            return Ok(());
+        }
+
+        let rows = if psrcat::is_db(&text) {
+            psrcat::parse_db(&text)
+        } else if psrcat::is_table(&text) {
+            psrcat::parse_table(&text)
+        } else {
+            text.lines()
+                .enumerate()
+                .map(|(n, l)| (n + 1, l.split_whitespace().collect::<Vec<_>>()))
+                .filter(|(_, ws)| {
+                    ws.first().is_some_and(|w| !w.starts_with('#'))
+                })
+                .map(|(n, ws)| ImportRow::new(n, PulsarMeta::from_strs(&ws)))
+                .collect()
+        };
 
-        Ok(results)
+        self.start_import(ImportPreview::new(path, rows), archivist);
+        Ok(())
     }
 
-    pub(crate) fn select_with_id(&mut self, id: i32) {
-        let data = self.downloader.data();
-        for (index, item) in data.iter().enumerate() {
-            if item.id == id {
-                self.downloader.select(index);
-                return;
+    fn start_import(
+        &mut self,
+        mut preview: ImportPreview,
+        archivist: &Syncher,
+    ) {
+        // Conflicts are checked against what the database has
+        preview.await_resync(self.downloader.synced());
+        self.import = Some(preview);
+        archivist.request(Request::Resync(DATA_TYPE));
+    }
+
+    /// Shows the column mapping of a CSV/TSV file, if any.
+    fn mapping_modal(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        let Some(mapping) = &mut self.mapping else {
+            return;
+        };
+
+        match mapping.show(ctx, &mut self.csv_mappings) {
+            MappingAction::None => return,
+            MappingAction::Cancel => {}
+            MappingAction::Done(preview) => {
+                self.start_import(preview, archivist);
             }
         }
+        self.mapping = None;
+    }
+
+    /// Restores remembered CSV/TSV column mappings.
+    pub fn load_mappings(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(saved) = eframe::get_value(storage, csv::STORAGE_KEY) {
+            self.csv_mappings = saved;
+        }
+    }
+
+    pub fn save_mappings(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, csv::STORAGE_KEY, &self.csv_mappings);
+    }
+
+    /// Shows the import preview, if any, and sends off what was accepted.
+    fn import_modal(&mut self, ctx: &egui::Context, archivist: &Syncher) {
+        let Some(preview) = &mut self.import else {
+            return;
+        };
+
+        preview.set_existing(self.downloader.synced(), self.downloader.data());
+
+        match preview.show(ctx) {
+            ImportAction::None => return,
+            ImportAction::Cancel => {}
+            ImportAction::Import { add, merge } => {
+                for meta in add {
+                    archivist.request(Request::AddPulsar(meta));
+                }
+                if !merge.is_empty() {
+                    self.merge_into(archivist, &merge);
+                }
+            }
+        }
+        self.import = None;
+    }
+
+    /// Adds a fetched pulsar, selected and in the form.
+    pub fn add(&mut self, pulsar: PulsarMeta) {
+        let id = pulsar.id;
+        self.downloader.add(pulsar);
+        self.select_with_id(id);
+    }
+
+    /// Selects the pulsar, if loaded, as if its row was clicked, and fills
+    /// the form with it. Does nothing if it's already selected, rather than
+    /// toggling it off. Returns whether it was loaded.
+    pub(crate) fn select_with_id(&mut self, id: i32) -> bool {
+        let data = self.downloader.data();
+        let Some(index) = data.iter().position(|p| p.id == id) else {
+            return false;
+        };
+
+        self.new_pulsar = data[index].clone();
+        if self.downloader.selected() != Some(index) {
+            self.downloader.select(index);
+        }
+        true
     }
 }
@@ -0,0 +1,206 @@
+//! Finding loaded pulsars near a position on the sky.
+
+use arpa::data_types::PulsarMeta;
+use egui_extras::{Column, TableBuilder};
+
+use crate::app::helpers::coordinates::{self, parse_dec, parse_ra};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Arcsec,
+    Arcmin,
+    Degree,
+}
+
+impl Unit {
+    const fn degrees(self) -> f64 {
+        match self {
+            Self::Arcsec => 1.0 / 3600.0,
+            Self::Arcmin => 1.0 / 60.0,
+            Self::Degree => 1.0,
+        }
+    }
+
+    const fn symbol(self) -> &'static str {
+        match self {
+            Self::Arcsec => "″",
+            Self::Arcmin => "′",
+            Self::Degree => "°",
+        }
+    }
+}
+
+pub struct ConeSearch {
+    pub open: bool,
+    ra: String,
+    dec: String,
+    radius: f64,
+    unit: Unit,
+}
+
+impl ConeSearch {
+    pub const fn new() -> Self {
+        Self {
+            open: false,
+            ra: String::new(),
+            dec: String::new(),
+            radius: 10.0,
+            unit: Unit::Arcmin,
+        }
+    }
+
+    /// Shows the search window. `selected` can fill in the centre. Returns
+    /// the id of a clicked result.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        pulsars: &[PulsarMeta],
+        selected: Option<&PulsarMeta>,
+    ) -> Option<i32> {
+        let mut open = self.open;
+        let mut clicked = None;
+
+        egui::Window::new("Cone search")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                self.inputs(ui, selected);
+                ui.separator();
+
+                let centre = match (parse_ra(&self.ra), parse_dec(&self.dec)) {
+                    (Ok(ra), Ok(dec)) => (ra, dec),
+                    (Err(err), _) | (_, Err(err)) => {
+                        if !self.ra.is_empty() || !self.dec.is_empty() {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                err.to_string(),
+                            );
+                        }
+                        return;
+                    }
+                };
+
+                let hits = self.search(pulsars, centre);
+                ui.label(format!("{} within the radius.", hits.len()));
+                clicked = self.results(ui, &hits);
+            });
+
+        self.open = open;
+        clicked
+    }
+
+    fn inputs(&mut self, ui: &mut egui::Ui, selected: Option<&PulsarMeta>) {
+        egui::Grid::new("cone_search_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                ui.label("RA");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.ra)
+                        .hint_text("hh:mm:ss or degrees"),
+                );
+                ui.end_row();
+
+                ui.label("DEC");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.dec)
+                        .hint_text("dd:mm:ss or degrees"),
+                );
+                ui.end_row();
+
+                ui.label("Radius");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.radius)
+                            .range(0.0..=180.0)
+                            .speed(0.1),
+                    );
+                    for unit in [Unit::Arcsec, Unit::Arcmin, Unit::Degree] {
+                        ui.selectable_value(
+                            &mut self.unit,
+                            unit,
+                            unit.symbol(),
+                        );
+                    }
+                });
+                ui.end_row();
+            });
+
+        let centre = selected.and_then(|p| {
+            Some((p, p.j2000_ra.as_ref()?, p.j2000_dec.as_ref()?))
+        });
+        let text = centre.map_or_else(
+            || "Around selected".to_string(),
+            |(p, _, _)| format!("Around {}", p.alias),
+        );
+        if ui
+            .add_enabled(centre.is_some(), egui::Button::new(text))
+            .clicked()
+            && let Some((_, ra, dec)) = centre
+        {
+            self.ra.clone_from(ra);
+            self.dec.clone_from(dec);
+        }
+    }
+
+    /// Pulsars within the radius, nearest first, with separations in
+    /// degrees.
+    fn search<'a>(
+        &self,
+        pulsars: &'a [PulsarMeta],
+        (ra, dec): (f64, f64),
+    ) -> Vec<(&'a PulsarMeta, f64)> {
+        let radius = self.radius * self.unit.degrees();
+        let mut hits = pulsars
+            .iter()
+            .filter_map(|p| {
+                let (pra, pdec) = super::equatorial(p)?;
+                let separation = coordinates::separation(ra, dec, pra, pdec);
+                (separation <= radius).then_some((p, separation))
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    fn results(
+        &self,
+        ui: &mut egui::Ui,
+        hits: &[(&PulsarMeta, f64)],
+    ) -> Option<i32> {
+        let mut clicked = None;
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .column(Column::auto().at_least(120.0))
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong("Pulsar");
+                });
+                header.col(|ui| {
+                    ui.strong("Separation");
+                });
+            })
+            .body(|body| {
+                body.rows(18.0, hits.len(), |mut row| {
+                    let (pulsar, separation) = hits[row.index()];
+                    row.col(|ui| {
+                        ui.label(&pulsar.alias);
+                    });
+                    row.col(|ui| {
+                        let value = separation / self.unit.degrees();
+                        ui.label(format!("{value:.3}{}", self.unit.symbol()));
+                    });
+                    if row.response().clicked() {
+                        clicked = Some(pulsar.id);
+                    }
+                });
+            });
+
+        clicked
+    }
+}
@@ -0,0 +1,250 @@
+//! Reading pulsars from CSV/TSV files, whose columns can come in any order.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use arpa::{ARPAError, data_types::PulsarMeta};
+
+use super::import::{ImportPreview, ImportRow};
+use crate::app::helpers::non_empty;
+
+/// The fields a source column can be mapped to.
+const FIELDS: [&str; 5] = ["Alias", "J name", "B name", "RA", "DEC"];
+
+/// Header names we guess a field from, lowercase.
+const GUESSES: [&[&str]; 5] = [
+    &["alias", "name", "psr", "pulsar"],
+    &["j name", "jname", "j_name", "psrj"],
+    &["b name", "bname", "b_name", "psrb"],
+    &["ra", "raj", "ra (j2000)", "j2000_ra"],
+    &["dec", "decj", "dec (j2000)", "j2000_dec"],
+];
+
+/// How many rows to show while mapping.
+const SAMPLE_ROWS: usize = 5;
+
+/// Mappings by header row, as header names per field.
+pub type SavedMappings = BTreeMap<String, [Option<String>; 5]>;
+pub const STORAGE_KEY: &str = "pulsar-csv-mappings";
+
+/// Returns the delimiter, if the file looks like CSV or TSV. Without the
+/// extension, it takes a header row we can guess a field from, as plain pulsar
+/// lists can have tabs or commas as well.
+pub fn delimiter(path: &std::path::Path, text: &str) -> Option<char> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "csv" => Some(','),
+        "tsv" | "tab" => Some('\t'),
+        _ => {
+            let first = text.lines().find(|l| !l.trim().is_empty())?;
+            if first.starts_with('#') {
+                return None;
+            }
+            ['\t', ','].into_iter().find(|&d| {
+                let headers = split(first, d);
+                headers.len() > 1
+                    && headers.iter().any(|h| {
+                        let h = h.trim().to_lowercase();
+                        GUESSES.iter().any(|names| names.contains(&h.as_str()))
+                    })
+            })
+        }
+    }
+}
+
+/// Splits one line, honouring double quotes.
+fn split(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                fields.push(std::mem::take(&mut field));
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// A delimited file waiting for its columns to be mapped.
+pub struct ColumnMapping {
+    path: PathBuf,
+    headers: Vec<String>,
+    /// Line numbers and fields.
+    records: Vec<(usize, Vec<String>)>,
+    /// Source column per field.
+    columns: [Option<usize>; 5],
+}
+
+/// What the user decided in the mapping step.
+pub enum MappingAction {
+    None,
+    Cancel,
+    Done(ImportPreview),
+}
+
+impl ColumnMapping {
+    /// Reads the header and records. Uses a saved mapping for the same
+    /// header if there is one, and guesses otherwise.
+    pub fn new(
+        path: PathBuf,
+        text: &str,
+        delimiter: char,
+        saved: &SavedMappings,
+    ) -> Result<Self, ARPAError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty() && !l.starts_with('#'));
+
+        let Some((_, header)) = lines.next() else {
+            return Err(ARPAError::MalformedInput("file is empty".into()));
+        };
+        let headers = split(header, delimiter);
+        let records =
+            lines.map(|(n, l)| (n + 1, split(l, delimiter))).collect();
+
+        let find = |name: &str| headers.iter().position(|h| h == name);
+        let columns = saved.get(&headers.join("\t")).map_or_else(
+            || {
+                GUESSES.map(|names| {
+                    headers.iter().position(|h| {
+                        names.contains(&h.to_lowercase().as_str())
+                    })
+                })
+            },
+            |names| names.clone().map(|n| n.as_deref().and_then(find)),
+        );
+
+        Ok(Self {
+            path,
+            headers,
+            records,
+            columns,
+        })
+    }
+
+    /// Remembers the current mapping for files with the same header.
+    fn save(&self, saved: &mut SavedMappings) {
+        let names = self.columns.map(|c| c.map(|i| self.headers[i].clone()));
+        saved.insert(self.headers.join("\t"), names);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        saved: &mut SavedMappings,
+    ) -> MappingAction {
+        let mut action = MappingAction::None;
+
+        let modal = egui::Modal::new(egui::Id::new("pulsar_csv_mapping"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading(format!("Columns of {}", self.path.display()));
+            ui.add_space(8.0);
+
+            egui::Grid::new("csv_mapping_grid")
+                .num_columns(2)
+                .spacing([32.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| self.mapping_grid(ui));
+
+            ui.add_space(8.0);
+            ui.label(format!("First rows of {}:", self.records.len()));
+            egui::Grid::new("csv_sample_grid")
+                .striped(true)
+                .show(ui, |ui| self.sample_grid(ui));
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let named = self.columns[..3].iter().any(Option::is_some);
+                let next = ui
+                    .add_enabled(named, egui::Button::new("Continue"))
+                    .on_disabled_hover_text("Map at least one name.");
+                if next.clicked() {
+                    self.save(saved);
+                    action = MappingAction::Done(self.preview());
+                }
+                if ui.button("Cancel").clicked() {
+                    action = MappingAction::Cancel;
+                }
+            });
+        });
+
+        if response.should_close() {
+            action = MappingAction::Cancel;
+        }
+
+        action
+    }
+
+    fn mapping_grid(&mut self, ui: &mut egui::Ui) {
+        for (field, column) in FIELDS.iter().zip(&mut self.columns) {
+            ui.label(*field);
+            let text = column.map_or("(none)", |i| self.headers[i].as_str());
+            egui::ComboBox::from_id_salt(("csv_mapping", field))
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(column, None, "(none)");
+                    for (i, header) in self.headers.iter().enumerate() {
+                        ui.selectable_value(column, Some(i), header);
+                    }
+                });
+            ui.end_row();
+        }
+    }
+
+    fn sample_grid(&self, ui: &mut egui::Ui) {
+        for header in &self.headers {
+            ui.strong(header);
+        }
+        ui.end_row();
+
+        for (_, fields) in self.records.iter().take(SAMPLE_ROWS) {
+            for field in fields {
+                ui.label(field);
+            }
+            ui.end_row();
+        }
+    }
+
+    /// Applies the mapping to every record.
+    fn preview(&self) -> ImportPreview {
+        let rows = self
+            .records
+            .iter()
+            .map(|(n, fields)| {
+                let [alias, j_name, b_name, ra, dec] = self.columns.map(|c| {
+                    c.and_then(|i| fields.get(i)).and_then(|f| non_empty(f))
+                });
+                ImportRow::new(*n, make_meta(alias, j_name, b_name, ra, dec))
+            })
+            .collect();
+
+        ImportPreview::new(self.path.clone(), rows)
+    }
+}
+
+/// Without an alias, the J or B name stands in.
+fn make_meta(
+    alias: Option<String>,
+    j_name: Option<String>,
+    b_name: Option<String>,
+    ra: Option<String>,
+    dec: Option<String>,
+) -> Result<PulsarMeta, ARPAError> {
+    super::make_pulsar(alias, j_name, b_name, ra, dec)
+        .ok_or_else(|| ARPAError::MalformedInput("row has no name".into()))
+}
@@ -0,0 +1,176 @@
+//! Spotting pulsars that are already in the database under some name or
+//! position.
+
+use arpa::data_types::PulsarMeta;
+use egui::RichText;
+
+use crate::app::helpers::{coordinates, format_data_option};
+
+/// Closer than this, in degrees, two positions are taken to be the same
+/// pulsar. One arcsecond, as cluster pulsars can sit a few apart.
+const SAME_POSITION: f64 = 1.0 / 3600.0;
+
+/// A pulsar that a new one might duplicate, and why.
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub pulsar: PulsarMeta,
+    pub reasons: Vec<String>,
+}
+
+/// Finds the pulsars that share a name with `meta`, in any of the name
+/// fields, or sit at the same position.
+pub fn find(meta: &PulsarMeta, others: &[PulsarMeta]) -> Vec<Duplicate> {
+    others
+        .iter()
+        .filter_map(|other| {
+            let reasons = reasons(meta, other);
+            (!reasons.is_empty()).then(|| Duplicate {
+                pulsar: other.clone(),
+                reasons,
+            })
+        })
+        .collect()
+}
+
+/// Why `a` and `b` look like the same pulsar, if they do.
+pub fn reasons(a: &PulsarMeta, b: &PulsarMeta) -> Vec<String> {
+    let theirs = names(b);
+    let mut reasons = names(a)
+        .into_iter()
+        .filter(|name| theirs.iter().any(|n| n.eq_ignore_ascii_case(name)))
+        .map(|name| format!("shares the name {name}"))
+        .collect::<Vec<_>>();
+    reasons.dedup();
+
+    if let (Some((ra1, dec1)), Some((ra2, dec2))) =
+        (super::equatorial(a), super::equatorial(b))
+    {
+        let separation = coordinates::separation(ra1, dec1, ra2, dec2);
+        if separation < SAME_POSITION {
+            reasons.push(format!("lies {:.2}″ away", separation * 3600.0));
+        }
+    }
+
+    reasons
+}
+
+/// The alias and the J and B names, whichever there are.
+fn names(meta: &PulsarMeta) -> Vec<&str> {
+    [
+        Some(&meta.alias),
+        meta.j_name.as_ref(),
+        meta.b_name.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(String::as_str)
+    .collect()
+}
+
+/// Fills in what `existing` lacks from `new`. What it already has stays.
+pub fn merge(existing: &PulsarMeta, new: &PulsarMeta) -> PulsarMeta {
+    let mut merged = existing.clone();
+    for (field, value) in [
+        (&mut merged.j_name, &new.j_name),
+        (&mut merged.b_name, &new.b_name),
+        (&mut merged.j2000_ra, &new.j2000_ra),
+        (&mut merged.j2000_dec, &new.j2000_dec),
+    ] {
+        if field.is_none() {
+            field.clone_from(value);
+        }
+    }
+    // A differing alias is worth keeping as a name, if there is room
+    let known = names(&merged);
+    if !known.iter().any(|n| n.eq_ignore_ascii_case(&new.alias)) {
+        if merged.j_name.is_none() && new.alias.starts_with('J') {
+            merged.j_name = Some(new.alias.clone());
+        } else if merged.b_name.is_none() && new.alias.starts_with('B') {
+            merged.b_name = Some(new.alias.clone());
+        }
+    }
+    merged
+}
+
+/// A pulsar on hold, as it looks like one we have.
+pub struct DuplicateCheck {
+    pub pulsar: PulsarMeta,
+    pub duplicates: Vec<Duplicate>,
+}
+
+/// What the user decided about a likely duplicate.
+pub enum DuplicateAction {
+    None,
+    Cancel,
+    AddAnyway,
+    /// Merge into the pulsar with this id.
+    Merge(i32),
+}
+
+impl DuplicateCheck {
+    pub fn show(&self, ctx: &egui::Context) -> DuplicateAction {
+        let mut action = DuplicateAction::None;
+
+        let modal = egui::Modal::new(egui::Id::new("pulsar_duplicates"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading(format!("{} may already exist", self.pulsar.alias));
+            ui.add_space(8.0);
+
+            egui::Grid::new("pulsar_duplicates_grid")
+                .num_columns(4)
+                .spacing([16.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    for duplicate in &self.duplicates {
+                        let other = &duplicate.pulsar;
+                        ui.label(
+                            RichText::new(format!(
+                                "#{} {}",
+                                other.id, other.alias
+                            ))
+                            .strong(),
+                        );
+                        ui.horizontal(|ui| {
+                            for coordinate in
+                                [&other.j2000_ra, &other.j2000_dec]
+                            {
+                                ui.label(format_data_option(
+                                    coordinate.as_ref(),
+                                ));
+                            }
+                        });
+                        ui.label(duplicate.reasons.join(", "));
+                        if ui
+                            .button("Merge")
+                            .on_hover_text(format!(
+                                "Fill in what #{} is missing, instead of \
+                                adding a new pulsar",
+                                other.id
+                            ))
+                            .clicked()
+                        {
+                            action = DuplicateAction::Merge(other.id);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.add_space(8.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Add anyway").clicked() {
+                    action = DuplicateAction::AddAnyway;
+                }
+                if ui.button("Cancel").clicked() {
+                    action = DuplicateAction::Cancel;
+                }
+            });
+        });
+
+        if response.should_close() {
+            action = DuplicateAction::Cancel;
+        }
+
+        action
+    }
+}
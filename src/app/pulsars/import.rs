@@ -0,0 +1,330 @@
+use std::path::PathBuf;
+
+use arpa::{ARPAError, data_types::PulsarMeta};
+use egui::RichText;
+use egui_extras::{Column, TableBuilder};
+
+use super::duplicates;
+use crate::app::helpers::{MISSING_DATA, format_data_option};
+
+/// One parsed entry of an import file.
+pub struct ImportRow {
+    /// Where in the file it came from, counting from 1.
+    pub line: usize,
+    pub result: Result<PulsarMeta, String>,
+    include: bool,
+}
+
+impl ImportRow {
+    /// Parses and verifies, so the preview shows what would be inserted.
+    pub fn new(line: usize, parsed: Result<PulsarMeta, ARPAError>) -> Self {
+        let result = parsed
+            .and_then(|mut meta| {
+                super::validate(&mut meta)?;
+                super::names::fill_j_name(&mut meta);
+                Ok(meta)
+            })
+            .map_err(|err| err.to_string());
+
+        Self {
+            line,
+            include: result.is_ok(),
+            result,
+        }
+    }
+}
+
+/// What the user decided in the preview.
+pub enum ImportAction {
+    None,
+    Cancel,
+    Import {
+        add: Vec<PulsarMeta>,
+        /// Existing pulsars by id, with what the file adds to them.
+        merge: Vec<(i32, PulsarMeta)>,
+    },
+}
+
+/// Why a row looks like a pulsar we already have.
+struct Conflict {
+    reason: String,
+    /// The pulsar in the database it would be merged into, if included.
+    existing: Option<PulsarMeta>,
+}
+
+/// A file's worth of pulsars, waiting to be looked over before import.
+pub struct ImportPreview {
+    file: PathBuf,
+    rows: Vec<ImportRow>,
+    /// The pulsar list's resync count when the preview asked for a resync.
+    requested: Option<u64>,
+    /// The pulsar list's resync count when conflicts were last checked.
+    existing: Option<u64>,
+    conflicts: Vec<Option<Conflict>>,
+}
+
+impl ImportPreview {
+    /// Rows that repeat earlier ones in the file start out left out.
+    pub fn new(file: PathBuf, rows: Vec<ImportRow>) -> Self {
+        let mut preview = Self {
+            file,
+            rows,
+            requested: None,
+            existing: None,
+            conflicts: Vec::new(),
+        };
+        preview.conflicts = preview.find_conflicts(&[]);
+        preview.exclude_new_conflicts(&[]);
+        preview
+    }
+
+    /// Holds off checking against the database until the pulsar list has
+    /// been resynced past `synced`.
+    pub const fn await_resync(&mut self, synced: u64) {
+        self.requested = Some(synced);
+    }
+
+    /// Checks the rows against the pulsars in the database, once the resync
+    /// is in and whenever they changed since. Rows that newly clash are left
+    /// out, what the user chose for the others stays.
+    pub fn set_existing(&mut self, synced: u64, pulsars: &[PulsarMeta]) {
+        if self.requested.is_some_and(|requested| synced <= requested)
+            || self.existing == Some(synced)
+        {
+            return;
+        }
+
+        self.existing = Some(synced);
+        let conflicts = self.find_conflicts(pulsars);
+        let before = std::mem::replace(&mut self.conflicts, conflicts);
+        self.exclude_new_conflicts(&before);
+    }
+
+    /// Leaves out the rows that clash now but didn't `before`.
+    fn exclude_new_conflicts(&mut self, before: &[Option<Conflict>]) {
+        for (i, (row, conflict)) in
+            self.rows.iter_mut().zip(&self.conflicts).enumerate()
+        {
+            let new = before.get(i).is_none_or(Option::is_none);
+            if conflict.is_some() && new {
+                row.include = false;
+            }
+        }
+    }
+
+    /// For each row, what it clashes with, if anything. Rows are compared
+    /// by name and position, both with the database and with earlier rows.
+    fn find_conflicts(&self, pulsars: &[PulsarMeta]) -> Vec<Option<Conflict>> {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let meta = row.result.as_ref().ok()?;
+
+                if let Some(found) = duplicates::find(meta, pulsars).pop() {
+                    let other = &found.pulsar;
+                    return Some(Conflict {
+                        reason: format!(
+                            "#{} {} ({})",
+                            other.id,
+                            other.alias,
+                            found.reasons.join(", ")
+                        ),
+                        existing: Some(found.pulsar),
+                    });
+                }
+
+                self.rows[..i].iter().find_map(|earlier| {
+                    let reasons = duplicates::reasons(
+                        meta,
+                        earlier.result.as_ref().ok()?,
+                    );
+                    (!reasons.is_empty()).then(|| Conflict {
+                        reason: format!(
+                            "line {} ({})",
+                            earlier.line,
+                            reasons.join(", ")
+                        ),
+                        existing: None,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> ImportAction {
+        let mut action = ImportAction::None;
+
+        let modal = egui::Modal::new(egui::Id::new("pulsar_import"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading(format!("Import {}", self.file.display()));
+            ui.add_space(8.0);
+
+            let valid = self.rows.iter().filter(|r| r.result.is_ok()).count();
+            ui.label(format!(
+                "{} entries, {valid} valid, {} conflicting.",
+                self.rows.len(),
+                self.conflicts.iter().flatten().count(),
+            ));
+
+            ui.horizontal(|ui| {
+                if ui.button("Include all valid").clicked() {
+                    for (row, conflict) in
+                        self.rows.iter_mut().zip(&self.conflicts)
+                    {
+                        row.include = row.result.is_ok() && conflict.is_none();
+                    }
+                }
+                if ui.button("Include none").clicked() {
+                    for row in &mut self.rows {
+                        row.include = false;
+                    }
+                }
+            });
+            ui.separator();
+
+            self.table(ui);
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let (add, merge) = self.included();
+                let label = if merge.is_empty() {
+                    format!("Import {}", add.len())
+                } else {
+                    format!("Import {}, merge {}", add.len(), merge.len())
+                };
+                let checked = self.existing.is_some();
+                let import = ui.add_enabled(
+                    checked && (!add.is_empty() || !merge.is_empty()),
+                    egui::Button::new(label),
+                );
+                if import.clicked() {
+                    action = ImportAction::Import { add, merge };
+                }
+                if ui.button("Cancel").clicked() {
+                    action = ImportAction::Cancel;
+                }
+                if !checked {
+                    ui.spinner();
+                    ui.label("Checking against the database...");
+                }
+            });
+        });
+
+        if response.should_close() {
+            action = ImportAction::Cancel;
+        }
+
+        action
+    }
+
+    fn table(&mut self, ui: &mut egui::Ui) {
+        let height = ui.ctx().screen_rect().height() * 0.6;
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .max_scroll_height(height)
+            .column(Column::auto())
+            .column(Column::auto())
+            .columns(Column::auto().at_least(64.0), 5)
+            .column(Column::remainder().at_least(160.0))
+            .header(20.0, |mut header| {
+                for title in
+                    ["", "Line", "Alias", "J name", "B name", "RA", "DEC"]
+                {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+                header.col(|ui| {
+                    ui.strong("Status");
+                });
+            })
+            .body(|body| {
+                body.rows(18.0, self.rows.len(), |mut row| {
+                    let index = row.index();
+                    let item = &mut self.rows[index];
+                    let conflict = &self.conflicts[index];
+
+                    row.col(|ui| {
+                        ui.add_enabled(
+                            item.result.is_ok(),
+                            egui::Checkbox::without_text(&mut item.include),
+                        );
+                    });
+                    row.col(|ui| {
+                        ui.label(item.line.to_string());
+                    });
+
+                    match &item.result {
+                        Ok(meta) => {
+                            row.col(|ui| {
+                                ui.label(RichText::new(&meta.alias).strong());
+                            });
+                            for field in [
+                                &meta.j_name,
+                                &meta.b_name,
+                                &meta.j2000_ra,
+                                &meta.j2000_dec,
+                            ] {
+                                row.col(|ui| {
+                                    ui.label(format_data_option(
+                                        field.as_ref(),
+                                    ));
+                                });
+                            }
+                        }
+                        Err(_) => {
+                            for _ in 0..5 {
+                                row.col(|ui| {
+                                    ui.label(MISSING_DATA);
+                                });
+                            }
+                        }
+                    }
+
+                    row.col(|ui| match (&item.result, conflict) {
+                        (Err(err), _) => {
+                            ui.colored_label(ui.visuals().error_fg_color, err);
+                        }
+                        (Ok(_), Some(conflict)) => {
+                            // Including a duplicate merges it
+                            let text = match &conflict.existing {
+                                Some(_) if item.include => {
+                                    format!("Merges into {}", conflict.reason)
+                                }
+                                _ => format!("Duplicates {}", conflict.reason),
+                            };
+                            ui.colored_label(ui.visuals().warn_fg_color, text);
+                        }
+                        (Ok(_), None) => {
+                            ui.label("OK");
+                        }
+                    });
+                });
+            });
+    }
+
+    /// What to add, and what to merge into existing pulsars.
+    fn included(&self) -> (Vec<PulsarMeta>, Vec<(i32, PulsarMeta)>) {
+        let mut add = Vec::new();
+        let mut merge = Vec::new();
+
+        let rows = self.rows.iter().zip(&self.conflicts);
+        for (row, conflict) in rows.filter(|(r, _)| r.include) {
+            let Ok(meta) = &row.result else {
+                continue;
+            };
+            if let Some(existing) =
+                conflict.as_ref().and_then(|c| c.existing.as_ref())
+            {
+                merge.push((existing.id, duplicates::merge(existing, meta)));
+            } else {
+                add.push(meta.clone());
+            }
+        }
+
+        (add, merge)
+    }
+}
@@ -0,0 +1,302 @@
+//! Merging two records of the same pulsar into one.
+
+use arpa::data_types::PulsarMeta;
+
+use super::{PulsarDependents, duplicates};
+use crate::app::helpers::format_data_option;
+
+/// Picks two pulsars, then compares them and asks how to merge.
+pub struct MergeDialog {
+    pub open: bool,
+    /// Ids of the two picked pulsars.
+    picked: [Option<i32>; 2],
+    preview: Option<MergePreview>,
+}
+
+/// Both records, with what refers to them.
+pub struct MergePreview {
+    sides: [PulsarDependents; 2],
+    /// Which side survives.
+    keep: usize,
+    /// Whether the survivor takes names and coordinates it lacks from the
+    /// other.
+    fill_missing: bool,
+    master: Option<i32>,
+}
+
+/// What the user asked for.
+pub enum MergeAction {
+    None,
+    Cancel,
+    /// Compare these two.
+    Preview(i32, i32),
+    Merge {
+        keep: i32,
+        lose: i32,
+        /// The survivor as it should end up, master included.
+        pulsar: PulsarMeta,
+    },
+}
+
+impl MergeDialog {
+    pub const fn new() -> Self {
+        Self {
+            open: false,
+            picked: [None, None],
+            preview: None,
+        }
+    }
+
+    /// Opens the picker with the given pulsar on the left.
+    pub const fn start(&mut self, id: Option<i32>) {
+        self.open = true;
+        if id.is_some() {
+            self.picked[0] = id;
+        }
+    }
+
+    pub fn set_preview(&mut self, sides: [PulsarDependents; 2]) {
+        // The one with more history is the better guess
+        let weight = |d: &PulsarDependents| d.ephemerides.len() + d.toas.len();
+        let keep = usize::from(weight(&sides[1]) > weight(&sides[0]));
+
+        self.preview = Some(MergePreview {
+            master: sides[keep]
+                .pulsar
+                .master_parfile_id
+                .or(sides[1 - keep].pulsar.master_parfile_id),
+            sides,
+            keep,
+            fill_missing: true,
+        });
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        pulsars: &[PulsarMeta],
+    ) -> MergeAction {
+        if let Some(preview) = &mut self.preview {
+            let action = preview.show(ctx);
+            match action {
+                MergeAction::None => {}
+                // Back to picking
+                MergeAction::Cancel => self.preview = None,
+                MergeAction::Preview(..) | MergeAction::Merge { .. } => {
+                    self.preview = None;
+                    self.open = false;
+                }
+            }
+            return action;
+        }
+
+        let mut action = MergeAction::None;
+        let mut open = self.open;
+
+        egui::Window::new("Merge pulsars")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Moves the ephemerides and TOAs of one record to the \
+                    other, and deletes it.",
+                );
+                ui.add_space(8.0);
+
+                egui::Grid::new("merge_pick_grid")
+                    .num_columns(2)
+                    .spacing([16.0, 4.0])
+                    .show(ui, |ui| {
+                        for (i, picked) in self.picked.iter_mut().enumerate() {
+                            ui.label(if i == 0 { "Merge" } else { "with" });
+                            pick(ui, i, picked, pulsars);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.add_space(8.0);
+                let ready = match self.picked {
+                    [Some(a), Some(b)] if a != b => Some((a, b)),
+                    _ => None,
+                };
+                let compare = ui
+                    .add_enabled(ready.is_some(), egui::Button::new("Compare"))
+                    .on_disabled_hover_text("Pick two different pulsars.");
+                if compare.clicked()
+                    && let Some((a, b)) = ready
+                {
+                    action = MergeAction::Preview(a, b);
+                }
+            });
+
+        self.open = open;
+        action
+    }
+}
+
+/// A drop-down of the loaded pulsars.
+fn pick(
+    ui: &mut egui::Ui,
+    salt: usize,
+    picked: &mut Option<i32>,
+    pulsars: &[PulsarMeta],
+) {
+    let text = picked
+        .and_then(|id| pulsars.iter().find(|p| p.id == id))
+        .map_or_else(|| "(pick)".to_string(), label);
+
+    egui::ComboBox::from_id_salt(("merge_pick", salt))
+        .selected_text(text)
+        .height(320.0)
+        .show_ui(ui, |ui| {
+            for pulsar in pulsars {
+                ui.selectable_value(picked, Some(pulsar.id), label(pulsar));
+            }
+        });
+}
+
+fn label(pulsar: &PulsarMeta) -> String {
+    format!("#{} {}", pulsar.id, pulsar.alias)
+}
+
+impl MergePreview {
+    /// The survivor as it would be stored.
+    fn result(&self) -> PulsarMeta {
+        let keep = &self.sides[self.keep].pulsar;
+        let lose = &self.sides[1 - self.keep].pulsar;
+
+        let mut pulsar = if self.fill_missing {
+            duplicates::merge(keep, lose)
+        } else {
+            keep.clone()
+        };
+        pulsar.master_parfile_id = self.master;
+        pulsar
+    }
+
+    fn show(&mut self, ctx: &egui::Context) -> MergeAction {
+        let mut action = MergeAction::None;
+
+        let modal = egui::Modal::new(egui::Id::new("pulsar_merge"));
+        let response = modal.show(ctx, |ui| {
+            ui.heading("Merge pulsars");
+            ui.add_space(8.0);
+
+            egui::Grid::new("merge_compare_grid")
+                .num_columns(3)
+                .spacing([32.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| self.compare_grid(ui));
+
+            ui.add_space(8.0);
+            ui.checkbox(
+                &mut self.fill_missing,
+                "Fill in names and coordinates the survivor lacks",
+            );
+            self.master_choice(ui);
+
+            ui.add_space(8.0);
+            let result = self.result();
+            ui.label(format!(
+                "Keeps #{} as {}, and deletes #{}. Review, then commit or \
+                roll back.",
+                self.sides[self.keep].pulsar.id,
+                result.alias,
+                self.sides[1 - self.keep].pulsar.id,
+            ));
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Merge").clicked() {
+                    action = MergeAction::Merge {
+                        keep: self.sides[self.keep].pulsar.id,
+                        lose: self.sides[1 - self.keep].pulsar.id,
+                        pulsar: result,
+                    };
+                }
+                if ui.button("Cancel").clicked() {
+                    action = MergeAction::Cancel;
+                }
+            });
+        });
+
+        if response.should_close() {
+            action = MergeAction::Cancel;
+        }
+
+        action
+    }
+
+    fn compare_grid(&mut self, ui: &mut egui::Ui) {
+        ui.label("");
+        for (i, side) in self.sides.iter().enumerate() {
+            ui.radio_value(
+                &mut self.keep,
+                i,
+                format!("Keep #{}", side.pulsar.id),
+            );
+        }
+        ui.end_row();
+
+        let [a, b] = [&self.sides[0], &self.sides[1]];
+        let field =
+            |ui: &mut egui::Ui,
+             name: &str,
+             get: fn(&PulsarMeta) -> Option<&String>| {
+                ui.label(name);
+                for side in [a, b] {
+                    ui.label(format_data_option(get(&side.pulsar)));
+                }
+                ui.end_row();
+            };
+        field(ui, "Alias", |p| Some(&p.alias));
+        field(ui, "J name", |p| p.j_name.as_ref());
+        field(ui, "B name", |p| p.b_name.as_ref());
+        field(ui, "RA", |p| p.j2000_ra.as_ref());
+        field(ui, "DEC", |p| p.j2000_dec.as_ref());
+
+        ui.label("Ephemerides");
+        for side in [a, b] {
+            ui.label(side.ephemerides.len().to_string());
+        }
+        ui.end_row();
+
+        ui.label("TOAs");
+        for side in [a, b] {
+            ui.label(side.toas.len().to_string());
+        }
+        ui.end_row();
+    }
+
+    /// The master ephemeride, out of both records' ephemerides.
+    fn master_choice(&mut self, ui: &mut egui::Ui) {
+        let ephemerides = self
+            .sides
+            .iter()
+            .flat_map(|s| &s.ephemerides)
+            .collect::<Vec<_>>();
+        let text = self
+            .master
+            .and_then(|m| ephemerides.iter().find(|e| e.id == m))
+            .map_or_else(
+                || "(none)".to_string(),
+                |e| format!("#{} {}", e.id, e.path),
+            );
+
+        ui.horizontal(|ui| {
+            ui.label("Master ephemeride");
+            egui::ComboBox::from_id_salt("merge_master")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.master, None, "(none)");
+                    for par in ephemerides {
+                        ui.selectable_value(
+                            &mut self.master,
+                            Some(par.id),
+                            format!("#{} {}", par.id, par.path),
+                        );
+                    }
+                });
+        });
+    }
+}
@@ -0,0 +1,272 @@
+//! Checking J and B names against the coordinates.
+
+use arpa::data_types::PulsarMeta;
+use egui::RichText;
+use egui_extras::{Column, TableBuilder};
+
+use crate::app::helpers::coordinates;
+
+/// The names a pulsar's coordinates call for, where they disagree with the
+/// ones it has. B names are historical and only approximately follow from
+/// the coordinates, so they are never fixed, see [`check_b_name`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameFix {
+    pub j_name: Option<String>,
+}
+
+impl NameFix {
+    pub const fn is_empty(&self) -> bool {
+        self.j_name.is_none()
+    }
+
+    /// Puts the expected names in place. An alias that is the J name is just
+    /// as wrong, so it goes along.
+    pub fn apply(&self, meta: &mut PulsarMeta) {
+        if let Some(j) = &self.j_name {
+            // Without a J name, `check` took the alias for it
+            if meta.j_name.as_ref().is_none_or(|old| *old == meta.alias) {
+                meta.alias.clone_from(j);
+            }
+            meta.j_name = Some(j.clone());
+        }
+    }
+}
+
+/// Fills in a missing J name from the coordinates.
+pub fn fill_j_name(meta: &mut PulsarMeta) {
+    if meta.j_name.is_none()
+        && let Some((ra, dec)) = super::equatorial(meta)
+    {
+        let j_name = coordinates::j_name(ra, dec);
+        // An alias that is the J name already says it
+        if j_name != meta.alias {
+            meta.j_name = Some(j_name);
+        }
+    }
+}
+
+/// Compares the names with what the coordinates give. Names without
+/// coordinates, or without names, pass.
+pub fn check(meta: &PulsarMeta) -> NameFix {
+    let Some((ra, dec)) = super::equatorial(meta) else {
+        return NameFix::default();
+    };
+
+    // The alias often is the J name, which `verify` then clears
+    let j_name = meta.j_name.as_ref().or_else(|| {
+        let alias = &meta.alias;
+        looks_like(alias, 'J').then_some(alias)
+    });
+
+    NameFix {
+        j_name: mismatch(j_name, coordinates::j_name(ra, dec)),
+    }
+}
+
+/// The B name the coordinates roughly give, where it disagrees with the one
+/// the pulsar has. The precession to B1950 leaves out the E-terms, and B names
+/// were assigned from older positions, so this is for information only.
+pub fn check_b_name(meta: &PulsarMeta) -> Option<String> {
+    let (ra, dec) = super::equatorial(meta)?;
+    mismatch(meta.b_name.as_ref(), coordinates::b_name(ra, dec))
+}
+
+/// The expected name, with the suffix of the current one, if they disagree.
+fn mismatch(name: Option<&String>, expected: String) -> Option<String> {
+    let name = name?;
+    let (core, suffix) = split_suffix(name);
+    let matches =
+        core == expected || (core.len() >= 8 && expected.starts_with(core));
+    (!matches).then(|| expected + suffix)
+}
+
+/// Whether the text is shaped like a `J`/`B` name, `Xhhmm±dd...`.
+fn looks_like(name: &str, prefix: char) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some(prefix)
+        && chars.clone().take(4).all(|c| c.is_ascii_digit())
+        && matches!(chars.nth(4), Some('+' | '-'))
+}
+
+/// Splits off a trailing letter, as in `J1748-2446A`.
+fn split_suffix(name: &str) -> (&str, &str) {
+    let core = name.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    // The prefix letter is not a suffix
+    if core.is_empty() {
+        return (name, "");
+    }
+    name.split_at(core.len())
+}
+
+/// A window listing pulsars whose names disagree with their coordinates.
+pub struct NameCheck {
+    pub open: bool,
+}
+
+/// What the user asked for in the window.
+pub enum NameAction {
+    Select(i32),
+    /// Fix the pulsar with this id.
+    Fix(i32, NameFix),
+}
+
+impl NameCheck {
+    pub const fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        pulsars: &[PulsarMeta],
+    ) -> Vec<NameAction> {
+        let mut actions = Vec::new();
+        let mut open = self.open;
+
+        egui::Window::new("Name check")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                let flagged = pulsars
+                    .iter()
+                    .map(|p| (p, check(p), check_b_name(p)))
+                    .filter(|(_, fix, b_name)| {
+                        !fix.is_empty() || b_name.is_some()
+                    })
+                    .collect::<Vec<_>>();
+
+                if flagged.is_empty() {
+                    ui.label("All names agree with the coordinates.");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} pulsars disagree.", flagged.len()));
+                    if ui
+                        .button("Fix all")
+                        .on_hover_text("Fixes the J names.")
+                        .clicked()
+                    {
+                        actions.extend(
+                            flagged
+                                .iter()
+                                .filter(|(_, fix, _)| !fix.is_empty())
+                                .map(|(p, fix, _)| {
+                                    NameAction::Fix(p.id, fix.clone())
+                                }),
+                        );
+                    }
+                });
+                ui.label(
+                    RichText::new(
+                        "B names are only approximated from the coordinates, \
+                        and are left as they are.",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+
+                Self::table(ui, &flagged, &mut actions);
+            });
+
+        self.open = open;
+        actions
+    }
+
+    fn table(
+        ui: &mut egui::Ui,
+        flagged: &[(&PulsarMeta, NameFix, Option<String>)],
+        actions: &mut Vec<NameAction>,
+    ) {
+        TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .column(Column::auto().at_least(100.0))
+            .columns(Column::auto().at_least(140.0), 2)
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for title in ["Pulsar", "J name", "B name", ""] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(18.0, flagged.len(), |mut row| {
+                    let (pulsar, fix, b_name) = &flagged[row.index()];
+                    row.col(|ui| {
+                        ui.label(RichText::new(&pulsar.alias).strong());
+                    });
+
+                    // A J name might be standing in as the alias
+                    let current = pulsar
+                        .j_name
+                        .as_ref()
+                        .or_else(|| fix.j_name.as_ref().map(|_| &pulsar.alias));
+                    row.col(|ui| match (current, &fix.j_name) {
+                        (Some(now), Some(expected)) => {
+                            ui.label(format!("{now} → {expected}"));
+                        }
+                        (Some(now), None) => {
+                            ui.label(now);
+                        }
+                        (None, _) => {}
+                    });
+
+                    row.col(|ui| match (&pulsar.b_name, b_name) {
+                        (Some(now), Some(expected)) => {
+                            ui.label(format!("{now} (≈ {expected})"))
+                                .on_hover_text("For information only.");
+                        }
+                        (Some(now), None) => {
+                            ui.label(now);
+                        }
+                        (None, _) => {}
+                    });
+
+                    row.col(|ui| {
+                        if !fix.is_empty() && ui.button("Fix").clicked() {
+                            actions
+                                .push(NameAction::Fix(pulsar.id, fix.clone()));
+                        }
+                    });
+
+                    if row.response().clicked() {
+                        actions.push(NameAction::Select(pulsar.id));
+                    }
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix_j_name(j_name: Option<&str>, alias: &str) -> PulsarMeta {
+        let mut meta = PulsarMeta::null();
+        meta.alias = alias.to_string();
+        meta.j_name = j_name.map(ToString::to_string);
+        let fix = NameFix {
+            j_name: Some("J0437-4715".into()),
+        };
+        fix.apply(&mut meta);
+        meta
+    }
+
+    #[test]
+    fn alias_that_is_the_j_name_follows_it() {
+        let meta = fix_j_name(Some("J0437-4716"), "J0437-4716");
+        assert_eq!(meta.alias, "J0437-4715");
+        let meta = fix_j_name(None, "J0437-4716");
+        assert_eq!(meta.alias, "J0437-4715");
+    }
+
+    #[test]
+    fn other_alias_stays() {
+        let meta = fix_j_name(Some("J0437-4716"), "Nearest MSP");
+        assert_eq!(meta.alias, "Nearest MSP");
+        assert_eq!(meta.j_name.as_deref(), Some("J0437-4715"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Everything about one pulsar in one place: its ephemerides, and what its
+//! TOAs cover.
+
+use std::collections::BTreeMap;
+
+use arpa::data_types::{PulsarMeta, TOAInfo};
+use egui::RichText;
+
+use crate::app::{
+    ephemerides::ParData,
+    helpers::{detail_row, format_data_option},
+};
+
+/// What a pulsar's TOAs cover.
+#[derive(Debug, Default)]
+pub struct TOASummary {
+    pub count: usize,
+    /// First and last MJD.
+    pub mjd: Option<(f64, f64)>,
+    /// TOA counts by observing frequency, to the nearest MHz.
+    pub bands: Vec<(i64, usize)>,
+    pub processes: Vec<ProcessSummary>,
+}
+
+/// The TOAs one process generated.
+#[derive(Debug)]
+pub struct ProcessSummary {
+    pub id: i32,
+    pub count: usize,
+    pub mjd: (f64, f64),
+    pub frequency: (f32, f32),
+}
+
+impl TOASummary {
+    pub fn new(toas: &[TOAInfo]) -> Self {
+        let mut summary = Self {
+            count: toas.len(),
+            ..Self::default()
+        };
+
+        let mut bands = BTreeMap::new();
+        let mut processes = BTreeMap::<i32, ProcessSummary>::new();
+        for toa in toas {
+            let mjd = f64::from(toa.toa_int) + toa.toa_frac;
+            let frequency = toa.frequency;
+
+            summary.mjd =
+                Some(summary.mjd.map_or((mjd, mjd), |(first, last)| {
+                    (first.min(mjd), last.max(mjd))
+                }));
+
+            #[allow(clippy::cast_possible_truncation)]
+            let band = f64::from(frequency).round() as i64;
+            *bands.entry(band).or_insert(0) += 1;
+
+            processes
+                .entry(toa.process_id)
+                .and_modify(|p| {
+                    p.count += 1;
+                    p.mjd = (p.mjd.0.min(mjd), p.mjd.1.max(mjd));
+                    p.frequency = (
+                        p.frequency.0.min(frequency),
+                        p.frequency.1.max(frequency),
+                    );
+                })
+                .or_insert(ProcessSummary {
+                    id: toa.process_id,
+                    count: 1,
+                    mjd: (mjd, mjd),
+                    frequency: (frequency, frequency),
+                });
+        }
+
+        summary.bands = bands.into_iter().collect();
+        summary.processes = processes.into_values().collect();
+        summary
+    }
+}
+
+/// A window following the selected pulsar.
+pub struct Overview {
+    pub open: bool,
+    pulsar: Option<PulsarMeta>,
+    /// `None` until fetched.
+    toas: Option<TOASummary>,
+}
+
+/// What the user asked for in the overview.
+pub enum OverviewAction {
+    None,
+    /// Fetch the TOAs of the pulsar with this id.
+    Fetch(i32),
+    /// Show the ephemeride with this id in its tab.
+    OpenEphemeride(i32),
+}
+
+impl Overview {
+    pub const fn new() -> Self {
+        Self {
+            open: false,
+            pulsar: None,
+            toas: None,
+        }
+    }
+
+    /// Drops what was fetched, so it is fetched again.
+    pub fn forget(&mut self) {
+        self.pulsar = None;
+        self.toas = None;
+    }
+
+    pub fn set_toas(&mut self, id: i32, toas: TOASummary) {
+        if self.pulsar.as_ref().is_some_and(|p| p.id == id) {
+            self.toas = Some(toas);
+        }
+    }
+
+    /// Shows the selected pulsar, with its ephemerides once they're fetched.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        selected: Option<&PulsarMeta>,
+        ephemerides: Option<&[ParData]>,
+    ) -> OverviewAction {
+        if !self.open {
+            return OverviewAction::None;
+        }
+
+        let mut action = OverviewAction::None;
+        match selected {
+            Some(pulsar)
+                if self.pulsar.as_ref().is_some_and(|p| p.id == pulsar.id) =>
+            {
+                // Keep up with edits
+                self.pulsar = Some(pulsar.clone());
+            }
+            Some(pulsar) => {
+                self.forget();
+                self.pulsar = Some(pulsar.clone());
+                action = OverviewAction::Fetch(pulsar.id);
+            }
+            None => self.forget(),
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Pulsar overview")
+            .open(&mut open)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let Some(pulsar) = &self.pulsar else {
+                    ui.label("Select a pulsar to see everything about it.");
+                    return;
+                };
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    Self::names(ui, pulsar);
+                    ui.separator();
+                    let clicked =
+                        Self::ephemerides_section(ui, pulsar, ephemerides);
+                    if let Some(id) = clicked {
+                        action = OverviewAction::OpenEphemeride(id);
+                    }
+                    ui.separator();
+                    self.toas_section(ui);
+                });
+            });
+
+        self.open = open;
+        action
+    }
+
+    fn names(ui: &mut egui::Ui, pulsar: &PulsarMeta) {
+        ui.heading(RichText::new(&pulsar.alias).strong());
+        egui::Grid::new("overview_names_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                detail_row(ui, "ID", pulsar.id.to_string());
+                detail_row(
+                    ui,
+                    "J name",
+                    format_data_option(pulsar.j_name.as_ref()),
+                );
+                detail_row(
+                    ui,
+                    "B name",
+                    format_data_option(pulsar.b_name.as_ref()),
+                );
+                detail_row(
+                    ui,
+                    "RA",
+                    format_data_option(pulsar.j2000_ra.as_ref()),
+                );
+                detail_row(
+                    ui,
+                    "DEC",
+                    format_data_option(pulsar.j2000_dec.as_ref()),
+                );
+            });
+    }
+
+    /// Returns the id of a clicked ephemeride.
+    fn ephemerides_section(
+        ui: &mut egui::Ui,
+        pulsar: &PulsarMeta,
+        ephemerides: Option<&[ParData]>,
+    ) -> Option<i32> {
+        ui.strong("Ephemerides");
+        let Some(ephemerides) = ephemerides else {
+            ui.spinner();
+            return None;
+        };
+
+        let mut clicked = None;
+        let mut link = |ui: &mut egui::Ui, par: &ParData| {
+            if ui
+                .link(format!("#{} {}", par.id, par.path))
+                .on_hover_text("Show in the Ephemerides tab")
+                .clicked()
+            {
+                clicked = Some(par.id);
+            }
+        };
+
+        let master = pulsar
+            .master_parfile_id
+            .and_then(|m| ephemerides.iter().find(|e| e.id == m));
+        ui.horizontal(|ui| {
+            ui.label("Master:");
+            match master {
+                Some(par) => link(ui, par),
+                None => {
+                    ui.label(RichText::new("None").italics());
+                }
+            }
+        });
+
+        let others = ephemerides
+            .iter()
+            .filter(|e| Some(e.id) != pulsar.master_parfile_id)
+            .collect::<Vec<_>>();
+        if others.is_empty() {
+            ui.label(RichText::new("No other ephemerides").italics());
+        } else {
+            ui.label(format!("{} other(s):", others.len()));
+            for par in others {
+                link(ui, par);
+            }
+        }
+
+        clicked
+    }
+
+    fn toas_section(&self, ui: &mut egui::Ui) {
+        ui.strong("TOAs");
+        let Some(toas) = &self.toas else {
+            ui.spinner();
+            return;
+        };
+        let Some((first, last)) = toas.mjd else {
+            ui.label(RichText::new("No TOAs").italics());
+            return;
+        };
+
+        egui::Grid::new("overview_toas_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                detail_row(ui, "Count", toas.count.to_string());
+                detail_row(
+                    ui,
+                    "MJD span",
+                    format!(
+                        "{first:.3} – {last:.3} ({:.1} days)",
+                        last - first
+                    ),
+                );
+                let bands = toas
+                    .bands
+                    .iter()
+                    .map(|(mhz, n)| format!("{mhz} MHz ×{n}"))
+                    .collect::<Vec<_>>();
+                detail_row(ui, "Frequencies", bands.join(", "));
+            });
+
+        ui.add_space(8.0);
+        ui.label(format!(
+            "Generated by {} process(es):",
+            toas.processes.len()
+        ));
+        egui::Grid::new("overview_processes_grid")
+            .num_columns(4)
+            .spacing([16.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                for title in ["Process", "TOAs", "MJD", "MHz"] {
+                    ui.strong(title);
+                }
+                ui.end_row();
+
+                for process in &toas.processes {
+                    let (first, last) = process.mjd;
+                    let (low, high) = process.frequency;
+                    ui.label(format!("#{}", process.id));
+                    ui.label(process.count.to_string());
+                    ui.label(format!("{first:.3} – {last:.3}"));
+                    ui.label(if (high - low).abs() < 0.5 {
+                        format!("{low:.0}")
+                    } else {
+                        format!("{low:.0} – {high:.0}")
+                    });
+                    ui.end_row();
+                }
+            });
+    }
+}
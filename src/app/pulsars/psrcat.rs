@@ -0,0 +1,155 @@
+//! Reading pulsars from the ATNF catalogue, either its `.db` record files or
+//! the tables printed by `psrcat -c`.
+
+use arpa::{ARPAError, data_types::PulsarMeta};
+
+use super::import::ImportRow;
+
+/// The catalogue parameters we keep.
+const KEYS: [&str; 4] = ["PSRJ", "PSRB", "RAJ", "DECJ"];
+
+/// Whether the text is a `.db` file: `KEY value` lines in records ending
+/// with `@`.
+pub fn is_db(text: &str) -> bool {
+    text.lines().any(|l| l.starts_with('@'))
+        && text.lines().any(|l| {
+            l.split_whitespace()
+                .next()
+                .is_some_and(|k| k == "PSRJ" || k == "PSRB")
+        })
+}
+
+/// Whether the text is `psrcat -c` output: a header naming at least one of
+/// the names, between rules of dashes.
+pub fn is_table(text: &str) -> bool {
+    header(text).is_some()
+}
+
+/// Reads every record of a `.db` file.
+pub fn parse_db(text: &str) -> Vec<ImportRow> {
+    let mut rows = Vec::new();
+    let mut values = [None, None, None, None];
+    let mut start = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('@') {
+            if let Some(start) = start.take() {
+                rows.push(ImportRow::new(start, make_meta(&values)));
+            }
+            values = [None, None, None, None];
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let (Some(key), Some(value)) = (words.next(), words.next()) else {
+            continue;
+        };
+        if let Some(i) = KEYS.iter().position(|k| *k == key) {
+            values[i] = Some(value.to_string());
+            start.get_or_insert(n + 1);
+        }
+    }
+
+    // The last record may lack its `@`
+    if let Some(start) = start {
+        rows.push(ImportRow::new(start, make_meta(&values)));
+    }
+
+    rows
+}
+
+/// Reads every numbered row of a `psrcat -c` table.
+pub fn parse_table(text: &str) -> Vec<ImportRow> {
+    let Some((header_line, columns)) = header(text) else {
+        return Vec::new();
+    };
+
+    text.lines()
+        .enumerate()
+        .skip(header_line + 1)
+        .filter(|(_, l)| {
+            l.split_whitespace()
+                .next()
+                .is_some_and(|w| w.parse::<usize>().is_ok())
+        })
+        .map(|(n, line)| {
+            let tokens = tokens(line);
+            let mut values = [None, None, None, None];
+            for (i, at) in &columns {
+                // Values sit under their names, give or take
+                values[*i] = tokens
+                    .iter()
+                    .min_by_key(|(start, _)| start.abs_diff(*at))
+                    .map(|(_, token)| (*token).to_string());
+            }
+            ImportRow::new(n + 1, make_meta(&values))
+        })
+        .collect()
+}
+
+/// Finds the header line, and where each known name sits in it.
+fn header(text: &str) -> Option<(usize, Vec<(usize, usize)>)> {
+    let mut after_rule = false;
+    for (n, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("---") {
+            after_rule = true;
+            continue;
+        }
+        if !after_rule {
+            continue;
+        }
+
+        let columns = tokens(line)
+            .into_iter()
+            .filter_map(|(at, token)| {
+                let key = token.to_uppercase();
+                KEYS.iter().position(|k| *k == key).map(|i| (i, at))
+            })
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            after_rule = false;
+        } else {
+            return Some((n, columns));
+        }
+    }
+
+    None
+}
+
+/// Splits on whitespace, keeping where each token starts.
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                tokens.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &line[s..]));
+    }
+    tokens
+}
+
+/// Builds a meta from PSRJ, PSRB, RAJ and DECJ, in that order. psrcat marks
+/// missing values with `*`.
+fn make_meta(values: &[Option<String>; 4]) -> Result<PulsarMeta, ARPAError> {
+    let [j_name, b_name, ra, dec] =
+        values.clone().map(|v| v.filter(|v| v != "*"));
+
+    super::make_pulsar(None, j_name, b_name, ra, dec).ok_or_else(|| {
+        ARPAError::MalformedInput(
+            "psrcat entry has neither PSRJ nor PSRB".into(),
+        )
+    })
+}
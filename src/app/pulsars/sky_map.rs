@@ -0,0 +1,169 @@
+//! An all-sky Hammer-Aitoff projection of the loaded pulsars.
+
+use std::f64::consts::SQRT_2;
+
+use arpa::data_types::PulsarMeta;
+use egui::{Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::app::helpers::coordinates;
+
+/// How close the pointer must be to a point to pick it, in pixels.
+const PICK_RADIUS: f32 = 8.0;
+const POINT_RADIUS: f32 = 2.5;
+/// Degrees between grid lines.
+const GRID_STEP: i32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    Equatorial,
+    Galactic,
+}
+
+pub struct SkyMap {
+    frame: Frame,
+}
+
+impl SkyMap {
+    pub const fn new() -> Self {
+        Self {
+            frame: Frame::Equatorial,
+        }
+    }
+
+    /// Draws every pulsar with coordinates. Returns the id of a clicked one.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        pulsars: &[PulsarMeta],
+        selected: Option<i32>,
+    ) -> Option<i32> {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.frame, Frame::Equatorial, "RA/DEC");
+            ui.selectable_value(&mut self.frame, Frame::Galactic, "l/b");
+        });
+
+        let points = pulsars
+            .iter()
+            .filter_map(|p| Some((p, self.position(p)?)))
+            .collect::<Vec<_>>();
+
+        // 2:1, as the projection is
+        let available = ui.available_size();
+        let width = available.x.min(available.y * 2.0);
+        let (response, painter) =
+            ui.allocate_painter(Vec2::new(width, width / 2.0), Sense::click());
+        let rect = response.rect;
+
+        let visuals = ui.visuals();
+        let grid = Stroke::new(1.0, visuals.weak_text_color());
+        let dot = visuals.text_color();
+        let highlight = visuals.selection.bg_fill;
+
+        self.draw_grid(&painter, rect, grid);
+
+        let mut hovered = None;
+        let hover = response.hover_pos();
+        for (pulsar, (lon, lat)) in &points {
+            let pos = to_screen(rect, *lon, *lat);
+
+            let is_selected = selected == Some(pulsar.id);
+            let (radius, color) = if is_selected {
+                (POINT_RADIUS * 2.0, highlight)
+            } else {
+                (POINT_RADIUS, dot)
+            };
+            painter.circle_filled(pos, radius, color);
+
+            if let Some(hover) = hover {
+                let distance = hover.distance(pos);
+                let closer = hovered.is_none_or(|(_, d)| distance < d);
+                if distance < PICK_RADIUS && closer {
+                    hovered = Some((*pulsar, distance));
+                }
+            }
+        }
+
+        ui.label(format!(
+            "{} of {} pulsars have coordinates.",
+            points.len(),
+            pulsars.len()
+        ));
+
+        let (pulsar, _) = hovered?;
+        response.clone().on_hover_text_at_pointer(&pulsar.alias);
+        response.clicked().then_some(pulsar.id)
+    }
+
+    /// Longitude and latitude in the current frame, in degrees.
+    fn position(&self, pulsar: &PulsarMeta) -> Option<(f64, f64)> {
+        let ra = coordinates::parse_ra(pulsar.j2000_ra.as_ref()?).ok()?;
+        let dec = coordinates::parse_dec(pulsar.j2000_dec.as_ref()?).ok()?;
+        Some(match self.frame {
+            Frame::Equatorial => (ra, dec),
+            Frame::Galactic => coordinates::galactic(ra, dec),
+        })
+    }
+
+    fn draw_grid(&self, painter: &egui::Painter, rect: Rect, stroke: Stroke) {
+        let line = |points: Vec<(f64, f64)>| {
+            let points = points
+                .into_iter()
+                .map(|(lon, lat)| to_screen(rect, lon, lat))
+                .collect();
+            painter.add(egui::Shape::line(points, stroke));
+        };
+
+        // Just short of ±180°, so the edges don't wrap onto each other
+        let edge = 179.999;
+        let steps = 180 / GRID_STEP;
+        for step in -steps..=steps {
+            let lon = f64::from(step * GRID_STEP).clamp(-edge, edge);
+            line((-90..=90).map(|lat| (lon, f64::from(lat))).collect());
+        }
+        for step in (1 - steps / 2)..steps / 2 {
+            let lat = f64::from(step * GRID_STEP);
+            line(
+                (-180..=180)
+                    .map(|lon| (f64::from(lon).clamp(-edge, edge), lat))
+                    .collect(),
+            );
+        }
+
+        let label = match self.frame {
+            Frame::Equatorial => "RA 0h at centre, increasing left",
+            Frame::Galactic => "l 0° at centre, increasing left",
+        };
+        painter.text(
+            rect.left_bottom(),
+            egui::Align2::LEFT_BOTTOM,
+            label,
+            egui::FontId::proportional(12.0),
+            stroke.color,
+        );
+    }
+}
+
+/// Projects longitude and latitude, in degrees, onto the rectangle. The
+/// centre is longitude 0, which increases to the left as on the sky.
+fn to_screen(rect: Rect, lon: f64, lat: f64) -> Pos2 {
+    let (x, y) = hammer(lon, lat);
+    let half = rect.size() / 2.0;
+    #[allow(clippy::cast_possible_truncation)]
+    let offset = Vec2::new(
+        (-x / (2.0 * SQRT_2)) as f32 * half.x,
+        (-y / SQRT_2) as f32 * half.y,
+    );
+    rect.center() + offset
+}
+
+/// Hammer-Aitoff, with x in ±2√2 and y in ±√2.
+fn hammer(lon: f64, lat: f64) -> (f64, f64) {
+    // Longitude within ±180°
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    let (lon, lat) = (lon.to_radians(), lat.to_radians());
+
+    let z = lat.cos().mul_add((lon / 2.0).cos(), 1.0).sqrt();
+    let x = 2.0 * SQRT_2 * lat.cos() * (lon / 2.0).sin() / z;
+    let y = SQRT_2 * lat.sin() / z;
+    (x, y)
+}
@@ -1,14 +1,22 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use arpa::{
     ARPAError, Archivist, TableItem,
-    data_types::{ParMeta, PulsarMeta, RawMeta, TOAInfo, TemplateMeta},
+    data_types::{
+        ParMeta, ProcessInfo, PulsarMeta, RawMeta, TOAInfo, TemplateMeta, User,
+    },
     pipeline,
 };
 use log::info;
 
 use crate::app::{
-    ephemerides::ParData, helpers::downloader::FetchType, toas::TOAData,
+    ephemerides::{ParData, ParRelated},
+    helpers::downloader::{FetchType, Fingerprints},
+    pulsars::{
+        Duplicate, PulsarDependents, PulsarRelated, TOASummary,
+        find_duplicates, validate,
+    },
+    toas::{TOAData, TOAProcess, TOARelated},
 };
 
 #[derive(Debug)]
@@ -45,7 +53,41 @@ pub enum Message {
     /// Response for updating something.
     ItemUpdated(DataType, i32),
 
+    // ---- Resync ------------------------------------------------------------
+    /// The fingerprint of every row in a table.
+    Fingerprints(DataType, Fingerprints),
+    /// Pulsars changed since the last sync.
+    PulsarChanges(Vec<PulsarMeta>),
+    /// Ephemerides changed since the last sync.
+    EphemerideChanges(Vec<ParData>),
+    /// TOAs changed since the last sync.
+    TOAChanges(Vec<TOAData>),
+
+    // ---- Related -----------------------------------------------------------
+    /// What refers to the pulsar with the given id.
+    PulsarRelated(i32, PulsarRelated),
+    /// What the ephemeride with the given id refers to.
+    EphemerideRelated(i32, ParRelated),
+    /// What the TOA with the given id refers to.
+    TOARelated(i32, TOARelated),
+    /// The related records of an item couldn't be fetched.
+    RelatedFailed(DataType, i32, String),
+
     // ---- Pulsars -----------------------------------------------------------
+    /// What would go with a pulsar on deletion.
+    PulsarDependents(PulsarDependents),
+    /// Pulsars in the database that a new one looks like.
+    PulsarDuplicates(PulsarMeta, Vec<Duplicate>),
+    /// Two pulsars to be merged, with what refers to them.
+    MergePreview(Box<[PulsarDependents; 2]>),
+    /// Response for merging the second pulsar into the first.
+    PulsarsMerged(i32, i32),
+    /// The ephemerides of the pulsar with the given id.
+    PulsarEphemerides(i32, Vec<ParData>),
+    /// What the TOAs of the pulsar with the given id cover.
+    PulsarTOAs(i32, TOASummary),
+    /// Paths of the ephemerides with the given ids, if they exist.
+    ParPaths(Vec<(i32, Option<String>)>),
     /// Downloaded pulsar info.
     Pulsars(Vec<PulsarMeta>),
     /// Downloaded pulsar info.
@@ -83,11 +125,40 @@ pub enum Request {
     Download(DataType, FetchType),
     /// Delete something froma a table.
     DeleteItem(DataType, i32),
+    /// Get the records linked to an item.
+    Related(DataType, i32),
+    /// Get the fingerprints of a table's rows, to tell what changed.
+    Resync(DataType),
+    /// Get the rows with these ids again, as they changed.
+    Refetch(DataType, Vec<i32>),
 
     // ---- Pulsars -----------------------------------------------------------
     AddPulsar(PulsarMeta),
+    /// Look for pulsars a new one would duplicate, ahead of adding it.
+    CheckPulsar(PulsarMeta),
     /// Overwrite an existing pulsar.
     UpdatePulsar(i32, PulsarMeta),
+    /// Find what refers to a pulsar, ahead of deleting it.
+    PulsarDependents(i32),
+    /// Delete a pulsar along with its ephemerides and TOAs.
+    DeletePulsar(i32),
+    /// Get the ephemerides of a pulsar.
+    PulsarEphemerides(i32),
+    /// Summarize the TOAs of a pulsar.
+    PulsarTOAs(i32),
+    /// Set or clear the master ephemeride of a pulsar.
+    SetMaster(i32, Option<i32>),
+    /// Get the paths of some ephemerides.
+    ParPaths(Vec<i32>),
+    /// Find what refers to two pulsars, ahead of merging them.
+    MergePreview(i32, i32),
+    /// Move everything from one pulsar to another, then delete it.
+    MergePulsars {
+        keep: i32,
+        lose: i32,
+        /// What `keep` should end up as, master included.
+        pulsar: PulsarMeta,
+    },
 
     // ---- Ephemerides -------------------------------------------------------
     /// Add one ephemeride
@@ -128,6 +199,13 @@ impl std::fmt::Debug for Request {
             Self::DeleteItem(t, i) => {
                 f.debug_tuple("DeleteItem").field(t).field(i).finish()
             }
+            Self::Related(t, i) => {
+                f.debug_tuple("Related").field(t).field(i).finish()
+            }
+            Self::Resync(t) => f.debug_tuple("Resync").field(t).finish(),
+            Self::Refetch(t, ids) => {
+                f.debug_tuple("Refetch").field(t).field(&ids.len()).finish()
+            }
 
             // Self::DownloadAllPulsars => write!(f, "DownloadAllPulsars"),
             Self::Download(dt, ft) => {
@@ -136,9 +214,39 @@ impl std::fmt::Debug for Request {
             Self::AddPulsar(pm) => {
                 f.debug_tuple("AddPulsar").field(pm).finish()
             }
+            Self::CheckPulsar(pm) => {
+                f.debug_tuple("CheckPulsar").field(pm).finish()
+            }
             Self::UpdatePulsar(i, pm) => {
                 f.debug_tuple("UpdatePulsar").field(i).field(pm).finish()
             }
+            Self::PulsarDependents(i) => {
+                f.debug_tuple("PulsarDependents").field(i).finish()
+            }
+            Self::DeletePulsar(i) => {
+                f.debug_tuple("DeletePulsar").field(i).finish()
+            }
+            Self::PulsarEphemerides(i) => {
+                f.debug_tuple("PulsarEphemerides").field(i).finish()
+            }
+            Self::PulsarTOAs(i) => {
+                f.debug_tuple("PulsarTOAs").field(i).finish()
+            }
+            Self::SetMaster(i, m) => {
+                f.debug_tuple("SetMaster").field(i).field(m).finish()
+            }
+            Self::ParPaths(ids) => {
+                f.debug_tuple("ParPaths").field(&ids.len()).finish()
+            }
+            Self::MergePreview(a, b) => {
+                f.debug_tuple("MergePreview").field(a).field(b).finish()
+            }
+            Self::MergePulsars { keep, lose, pulsar } => f
+                .debug_struct("MergePulsars")
+                .field("keep", keep)
+                .field("lose", lose)
+                .field("pulsar", pulsar)
+                .finish(),
 
             Self::AddPar {
                 path,
@@ -205,13 +313,12 @@ impl Request {
                 .map_err(ARPAError::from),
 
             // ---- Generics --------------------------------------------------
-            Self::DeleteItem(dt, id) => match dt {
-                DataType::Pulsar => archivist.delete::<PulsarMeta>(id).await,
-                DataType::Ephemeride => archivist.delete::<ParMeta>(id).await,
-                DataType::Toa => archivist.delete::<TOAInfo>(id).await,
-            }
-            .map(|()| Message::ItemDeleted(dt, id))
-            .map_err(ARPAError::from),
+            Self::DeleteItem(dt, id) => delete_item(archivist, &dt, id)
+                .await
+                .map(|()| Message::ItemDeleted(dt, id)),
+            Self::Related(dt, id) => Ok(related(archivist, dt, id).await),
+            Self::Resync(dt) => resync(archivist, dt).await,
+            Self::Refetch(dt, ids) => refetch(archivist, dt, &ids).await,
 
             // ---- Pulsars ---------------------------------------------------
             Self::Download(DataType::Pulsar, FetchType::All) => archivist
@@ -234,6 +341,15 @@ impl Request {
                 .await
                 .map(|()| Message::ItemUpdated(DataType::Pulsar, id))
                 .map_err(ARPAError::from),
+            Self::PulsarEphemerides(_)
+            | Self::PulsarTOAs(_)
+            | Self::SetMaster(..)
+            | Self::ParPaths(_)
+            | Self::CheckPulsar(_)
+            | Self::PulsarDependents(_)
+            | Self::DeletePulsar(_)
+            | Self::MergePreview(..)
+            | Self::MergePulsars { .. } => self.handle_pulsar(archivist).await,
 
             // ---- Ephemerides -----------------------------------------------
             Self::Download(DataType::Ephemeride, FetchType::All) => {
@@ -288,6 +404,49 @@ impl Request {
 
         response.unwrap_or_else(Message::Error)
     }
+
+    /// The pulsar requests beyond fetching, adding and overwriting.
+    async fn handle_pulsar(
+        self,
+        archivist: &mut Archivist,
+    ) -> Result<Message, ARPAError> {
+        match self {
+            Self::PulsarEphemerides(id) => get_pulsar_pars(archivist, id)
+                .await
+                .map(|pars| Message::PulsarEphemerides(id, pars)),
+            Self::PulsarTOAs(id) => get_pulsar_toas(archivist, id)
+                .await
+                .map(|summary| Message::PulsarTOAs(id, summary)),
+            Self::SetMaster(id, par) => set_master(archivist, id, par)
+                .await
+                .map(|()| Message::ItemUpdated(DataType::Pulsar, id)),
+            Self::ParPaths(ids) => {
+                get_par_paths(archivist, &ids).await.map(Message::ParPaths)
+            }
+            Self::CheckPulsar(meta) => {
+                let all = archivist.get_all().await?;
+                let duplicates = find_duplicates(&meta, &all);
+                Ok(Message::PulsarDuplicates(meta, duplicates))
+            }
+            Self::PulsarDependents(id) => get_pulsar_dependents(archivist, id)
+                .await
+                .map(Message::PulsarDependents),
+            Self::DeletePulsar(id) => delete_pulsar(archivist, id)
+                .await
+                .map(|()| Message::ItemDeleted(DataType::Pulsar, id)),
+            Self::MergePreview(a, b) => {
+                let first = get_pulsar_dependents(archivist, a).await?;
+                let second = get_pulsar_dependents(archivist, b).await?;
+                Ok(Message::MergePreview(Box::new([first, second])))
+            }
+            Self::MergePulsars { keep, lose, pulsar } => {
+                merge_pulsars(archivist, keep, lose, pulsar)
+                    .await
+                    .map(|()| Message::PulsarsMerged(keep, lose))
+            }
+            _ => unreachable!("{self:?} is not a pulsar request"),
+        }
+    }
 }
 
 async fn set_up_pipes(
@@ -337,6 +496,7 @@ async fn make_toa_data(
     Ok(TOAData {
         id: TableItem::id(&meta),
         process: meta.process_id,
+        pulsar_id: meta.pulsar_id,
         pulsar,
         observer: meta.observer_id,
         template: meta.template_id,
@@ -371,9 +531,376 @@ async fn make_par_data(
         pulsar_id: meta.pulsar_id,
         pulsar_name,
         path: meta.file_path,
+        checksum: meta.checksum.to_string(),
+    })
+}
+
+/// Failures go back to the detail panel, rather than leaving it waiting.
+async fn related(archivist: &Archivist, dt: DataType, id: i32) -> Message {
+    let related = match dt {
+        DataType::Pulsar => get_pulsar_related(archivist, id)
+            .await
+            .map(|related| Message::PulsarRelated(id, related)),
+        DataType::Ephemeride => get_par_related(archivist, id)
+            .await
+            .map(|related| Message::EphemerideRelated(id, related)),
+        DataType::Toa => get_toa_related(archivist, id)
+            .await
+            .map(|related| Message::TOARelated(id, related)),
+    };
+
+    related
+        .unwrap_or_else(|err| Message::RelatedFailed(dt, id, err.to_string()))
+}
+
+async fn resync(
+    archivist: &Archivist,
+    dt: DataType,
+) -> Result<Message, ARPAError> {
+    // Rows show their pulsar's alias, so a renamed pulsar changes them too
+    let alias = |table: arpa::Table| {
+        format!(
+            " || coalesce((select alias from {} \
+            where id = {table}.pulsar_id), '')",
+            PulsarMeta::TABLE,
+        )
+    };
+
+    let fingerprints = match dt {
+        DataType::Pulsar => fingerprints::<PulsarMeta>(archivist, "").await?,
+        DataType::Ephemeride => {
+            fingerprints::<ParMeta>(archivist, &alias(ParMeta::TABLE)).await?
+        }
+        DataType::Toa => {
+            fingerprints::<TOAInfo>(archivist, &alias(TOAInfo::TABLE)).await?
+        }
+    };
+    Ok(Message::Fingerprints(dt, fingerprints))
+}
+
+/// Hashes every row of a table on the database's side, so that only ids and
+/// hashes come over. `shown` is SQL for what the rows show from elsewhere,
+/// hashed along.
+async fn fingerprints<T: TableItem>(
+    archivist: &Archivist,
+    shown: &str,
+) -> Result<Fingerprints, ARPAError> {
+    let table = T::TABLE;
+    let hash = format!("('x' || left(md5({table}::text{shown}), 16))::bit(64)");
+    let columns = format!(
+        "coalesce(array_agg(id order by id), '{{}}'), \
+        coalesce(array_agg({hash}::bigint order by id), '{{}}')"
+    );
+
+    let (ids, hashes) = archivist
+        .get_special::<T, (Vec<i32>, Vec<i64>)>(&columns, "true")
+        .await?
+        .unwrap_or_default();
+    Ok(ids.into_iter().zip(hashes).collect())
+}
+
+async fn refetch(
+    archivist: &Archivist,
+    dt: DataType,
+    ids: &[i32],
+) -> Result<Message, ARPAError> {
+    Ok(match dt {
+        DataType::Pulsar => {
+            let mut pulsars = Vec::new();
+            for id in ids {
+                pulsars.push(archivist.get::<PulsarMeta>(*id).await?);
+            }
+            Message::PulsarChanges(pulsars)
+        }
+        DataType::Ephemeride => {
+            let mut pars = Vec::new();
+            for id in ids {
+                let meta = archivist.get::<ParMeta>(*id).await?;
+                pars.push(make_par_data(archivist, meta).await?);
+            }
+            Message::EphemerideChanges(pars)
+        }
+        DataType::Toa => {
+            let mut toas = Vec::new();
+            for id in ids {
+                let meta = archivist.get::<TOAInfo>(*id).await?;
+                toas.push(make_toa_data(archivist, meta).await?);
+            }
+            Message::TOAChanges(toas)
+        }
     })
 }
 
+async fn get_pulsar_related(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<PulsarRelated, ARPAError> {
+    let ephemerides = get_pulsar_pars(archivist, id).await?;
+    let toa_count =
+        count::<TOAInfo>(archivist, &format!("pulsar_id={id}")).await?;
+
+    Ok(PulsarRelated {
+        ephemerides,
+        toa_count,
+    })
+}
+
+async fn get_par_related(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<ParRelated, ARPAError> {
+    let meta = archivist.get::<ParMeta>(id).await?;
+    let pulsar = archivist.get::<PulsarMeta>(meta.pulsar_id).await?;
+
+    Ok(ParRelated { pulsar })
+}
+
+async fn get_toa_related(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<TOARelated, ARPAError> {
+    let meta = archivist.get::<TOAInfo>(id).await?;
+    let template = archivist.get::<TemplateMeta>(meta.template_id).await?;
+    let process = archivist.get::<ProcessInfo>(meta.process_id).await?;
+    let user = archivist.get::<User>(process.user_id).await?;
+
+    let process_toas =
+        count::<TOAInfo>(archivist, &format!("process_id={}", meta.process_id))
+            .await?;
+    let observer_toas = count::<TOAInfo>(
+        archivist,
+        &format!("observer_id={}", meta.observer_id),
+    )
+    .await?;
+
+    Ok(TOARelated {
+        template,
+        process: TOAProcess {
+            par_id: process.par_id,
+            template_id: process.template_id,
+            method: process.method,
+            started_at: process.started_at.to_string(),
+            user: format!("{} ({})", user.real_name(), user.username()),
+        },
+        process_toas,
+        observer_toas,
+    })
+}
+
+/// Deletes a single row, unless a pulsar still uses it as master.
+async fn delete_item(
+    archivist: &mut Archivist,
+    dt: &DataType,
+    id: i32,
+) -> Result<(), ARPAError> {
+    match dt {
+        DataType::Pulsar => archivist.delete::<PulsarMeta>(id).await?,
+        DataType::Ephemeride => {
+            let masters = archivist.get_all::<PulsarMeta>().await?;
+            if let Some(p) =
+                masters.iter().find(|p| p.master_parfile_id == Some(id))
+            {
+                return Err(ARPAError::MalformedInput(format!(
+                    "Ephemeride #{id} is the master of pulsar \"{}\"",
+                    p.alias
+                )));
+            }
+            archivist.delete::<ParMeta>(id).await?;
+        }
+        DataType::Toa => archivist.delete::<TOAInfo>(id).await?,
+    }
+    Ok(())
+}
+
+async fn get_pulsar_dependents(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<PulsarDependents, ARPAError> {
+    let pulsar = archivist.get::<PulsarMeta>(id).await?;
+    let ephemerides = get_pulsar_pars(archivist, id).await?;
+
+    let toas = archivist
+        .get_all::<TOAInfo>()
+        .await?
+        .iter()
+        .filter(|t| t.pulsar_id == id)
+        .map(TableItem::id)
+        .collect();
+
+    let borrowers = archivist
+        .get_all::<PulsarMeta>()
+        .await?
+        .into_iter()
+        .filter(|p| {
+            p.id != id
+                && p.master_parfile_id
+                    .is_some_and(|m| ephemerides.iter().any(|e| e.id == m))
+        })
+        .collect();
+
+    Ok(PulsarDependents {
+        pulsar,
+        ephemerides,
+        toas,
+        borrowers,
+    })
+}
+
+async fn get_pulsar_pars(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<Vec<ParData>, ARPAError> {
+    let ids = archivist
+        .get_special::<ParMeta, (Vec<i32>,)>(
+            "coalesce(array_agg(id order by id), '{}')",
+            &format!("pulsar_id={id}"),
+        )
+        .await?
+        .unwrap_or_default()
+        .0;
+
+    let mut ephemerides = Vec::new();
+    for id in ids {
+        let meta = archivist.get::<ParMeta>(id).await?;
+        ephemerides.push(make_par_data(archivist, meta).await?);
+    }
+    Ok(ephemerides)
+}
+
+/// Counts the rows of a table meeting a condition, in the database.
+async fn count<T: TableItem>(
+    archivist: &Archivist,
+    condition: &str,
+) -> Result<usize, ARPAError> {
+    let (count,) = archivist
+        .get_special::<T, (i64,)>("count(*)", condition)
+        .await?
+        .unwrap_or_default();
+    Ok(usize::try_from(count).unwrap_or_default())
+}
+
+async fn get_pulsar_toas(
+    archivist: &Archivist,
+    id: i32,
+) -> Result<TOASummary, ARPAError> {
+    archivist.assert_id(arpa::Table::PulsarMetas, id).await?;
+    let toas = archivist
+        .get_all::<TOAInfo>()
+        .await?
+        .into_iter()
+        .filter(|t| t.pulsar_id == id)
+        .collect::<Vec<_>>();
+    Ok(TOASummary::new(&toas))
+}
+
+/// Sets a pulsar's master ephemeride, which has to be one of its own.
+async fn set_master(
+    archivist: &mut Archivist,
+    id: i32,
+    par: Option<i32>,
+) -> Result<(), ARPAError> {
+    let mut meta = archivist.get::<PulsarMeta>(id).await?;
+    if let Some(par) = par {
+        let par_meta = archivist.get::<ParMeta>(par).await?;
+        if par_meta.pulsar_id != id {
+            return Err(ARPAError::MalformedInput(format!(
+                "Ephemeride #{par} belongs to another pulsar than \"{}\"",
+                meta.alias
+            )));
+        }
+    }
+
+    meta.master_parfile_id = par;
+    archivist.update_from_cache(&meta, id).await?;
+    Ok(())
+}
+
+async fn get_par_paths(
+    archivist: &Archivist,
+    ids: &[i32],
+) -> Result<Vec<(i32, Option<String>)>, ARPAError> {
+    let paths = archivist
+        .get_all::<ParMeta>()
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p.file_path))
+        .collect::<HashMap<_, _>>();
+
+    Ok(ids.iter().map(|id| (*id, paths.get(id).cloned())).collect())
+}
+
+/// Deletes a pulsar and everything referring to it. The master link goes
+/// first, so it never points at a deleted ephemeride.
+async fn delete_pulsar(
+    archivist: &mut Archivist,
+    id: i32,
+) -> Result<(), ARPAError> {
+    let deps = get_pulsar_dependents(archivist, id).await?;
+    if let Some(other) = deps.borrowers.first() {
+        return Err(ARPAError::MalformedInput(format!(
+            "Pulsar \"{}\" uses an ephemeride of \"{}\" as master",
+            other.alias, deps.pulsar.alias
+        )));
+    }
+
+    let mut pulsar = deps.pulsar;
+    if pulsar.master_parfile_id.is_some() {
+        pulsar.master_parfile_id = None;
+        archivist.update_from_cache(&pulsar, id).await?;
+    }
+    for toa in deps.toas {
+        archivist.delete::<TOAInfo>(toa).await?;
+    }
+    for par in deps.ephemerides {
+        archivist.delete::<ParMeta>(par.id).await?;
+    }
+    archivist.delete::<PulsarMeta>(id).await?;
+
+    Ok(())
+}
+
+/// Re-points the ephemerides and TOAs of `lose` to `keep`, deletes `lose`,
+/// and then overwrites `keep`, which may take names `lose` had. The master
+/// ephemeride has to be one of either pulsar's.
+async fn merge_pulsars(
+    archivist: &mut Archivist,
+    keep: i32,
+    lose: i32,
+    mut pulsar: PulsarMeta,
+) -> Result<(), ARPAError> {
+    if keep == lose {
+        return Err(ARPAError::MalformedInput(
+            "Cannot merge a pulsar with itself".into(),
+        ));
+    }
+    validate(&mut pulsar)?;
+    archivist.assert_id(arpa::Table::PulsarMetas, keep).await?;
+    if let Some(master) = pulsar.master_parfile_id {
+        let par = archivist.get::<ParMeta>(master).await?;
+        if par.pulsar_id != keep && par.pulsar_id != lose {
+            return Err(ARPAError::MalformedInput(format!(
+                "Ephemeride #{master} belongs to neither merged pulsar"
+            )));
+        }
+    }
+    let deps = get_pulsar_dependents(archivist, lose).await?;
+
+    for par in &deps.ephemerides {
+        let mut meta = archivist.get::<ParMeta>(par.id).await?;
+        meta.pulsar_id = keep;
+        archivist.update_from_cache(&meta, par.id).await?;
+    }
+    for toa in deps.toas {
+        let mut meta = archivist.get::<TOAInfo>(toa).await?;
+        meta.pulsar_id = keep;
+        archivist.update_from_cache(&meta, toa).await?;
+    }
+    archivist.delete::<PulsarMeta>(lose).await?;
+    archivist.update_from_cache(&pulsar, keep).await?;
+
+    Ok(())
+}
+
 async fn overwrite_par(
     archivist: &mut Archivist,
     id: i32,
@@ -1,10 +1,11 @@
+use arpa::data_types::TemplateMeta;
 use egui::RichText;
 
 use crate::app::{
     Request, Syncher,
     helpers::{
-        StatusMessage, StatusMessageSeverity,
-        downloader::{self, Downloader, DownloaderAction},
+        StatusMessage, StatusMessageSeverity, detail_row,
+        downloader::{self, Downloader, DownloaderAction, ItemColumn},
     },
 };
 
@@ -14,6 +15,8 @@ const DATA_TYPE: crate::app::DataType = crate::app::DataType::Toa;
 pub struct TOAData {
     pub id: i32,
     pub process: i32,
+    pub pulsar_id: i32,
+    /// The alias of the pulsar.
     pub pulsar: String,
     pub observer: i32,
     pub template: i32,
@@ -22,77 +25,185 @@ pub struct TOAData {
     pub error: f32,
 }
 
+/// The process that generated a TOA.
+#[derive(Debug)]
+pub struct TOAProcess {
+    pub par_id: Option<i32>,
+    pub template_id: i32,
+    pub method: String,
+    pub started_at: String,
+    /// The name of the user who launched it.
+    pub user: String,
+}
+
+/// What a TOA refers to.
+#[derive(Debug)]
+pub struct TOARelated {
+    pub template: TemplateMeta,
+    pub process: TOAProcess,
+    /// TOAs generated by the same process, this one included.
+    pub process_toas: usize,
+    /// TOAs from the same observer, this one included.
+    pub observer_toas: usize,
+}
+
 impl downloader::Item for TOAData {
     const NAME: &str = "TOA";
-    const COLUMNS: &[(&str, &str)] = &[
-        ("Pc.", "The ID of the process."),
-        ("Pulsar", "The ID of the pulsar."),
-        ("Time", "The arrival time."),
-        ("Error", "The error in the time."),
-        ("Ob.", "The ID of the observer."),
-        ("Tm.", "The ID of the template used."),
-        ("Frequency", "The observing frequency."),
+    const COLUMNS: &[ItemColumn<Self>] = &[
+        ItemColumn {
+            header: "Pc.",
+            hint: "The ID of the process.",
+            cell: |t, ui| {
+                ui.label(t.process.to_string());
+            },
+            text: |t| t.process.to_string(),
+            edit: None,
+            cmp: |a, b| a.process.cmp(&b.process),
+        },
+        ItemColumn {
+            header: "Pulsar",
+            hint: "The ID of the pulsar.",
+            cell: |t, ui| {
+                ui.label(&t.pulsar);
+            },
+            text: |t| t.pulsar.clone(),
+            edit: None,
+            cmp: |a, b| a.pulsar.cmp(&b.pulsar),
+        },
+        ItemColumn {
+            header: "Time",
+            hint: "The arrival time.",
+            cell: |t, ui| {
+                ui.label(t.time.to_string());
+            },
+            text: |t| t.time.to_string(),
+            edit: None,
+            cmp: |a, b| a.time.total_cmp(&b.time),
+        },
+        ItemColumn {
+            header: "Error",
+            hint: "The error in the time.",
+            cell: |t, ui| {
+                ui.label(t.error.to_string());
+            },
+            text: |t| t.error.to_string(),
+            edit: None,
+            cmp: |a, b| a.error.total_cmp(&b.error),
+        },
+        ItemColumn {
+            header: "Ob.",
+            hint: "The ID of the observer.",
+            cell: |t, ui| {
+                ui.label(t.observer.to_string());
+            },
+            text: |t| t.observer.to_string(),
+            edit: None,
+            cmp: |a, b| a.observer.cmp(&b.observer),
+        },
+        ItemColumn {
+            header: "Tm.",
+            hint: "The ID of the template used.",
+            cell: |t, ui| {
+                ui.label(t.template.to_string());
+            },
+            text: |t| t.template.to_string(),
+            edit: None,
+            cmp: |a, b| a.template.cmp(&b.template),
+        },
+        ItemColumn {
+            header: "Frequency",
+            hint: "The observing frequency.",
+            cell: |t, ui| {
+                ui.label(t.frequency.to_string());
+            },
+            text: |t| t.frequency.to_string(),
+            edit: None,
+            cmp: |a, b| a.frequency.total_cmp(&b.frequency),
+        },
     ];
+    const LINKS: &[&str] = &["Pulsar"];
+
+    type Related = TOARelated;
 
     fn id(&self) -> i32 {
         self.id
     }
 
-    fn cmp_by(&self, other: &Self, index: usize) -> std::cmp::Ordering {
-        match index {
-            0 => self.process.cmp(&other.process),
-            1 => self.pulsar.cmp(&other.pulsar),
-            2 => self.time.total_cmp(&other.time),
-            3 => self.error.total_cmp(&other.error),
-            4 => self.template.cmp(&other.template),
-            5 => self.observer.cmp(&other.observer),
-            6 => self.frequency.total_cmp(&other.frequency),
-            _ => std::cmp::Ordering::Equal,
-        }
+    fn details(&self, ui: &mut egui::Ui) {
+        detail_row(ui, "ID", self.id.to_string());
+        detail_row(ui, "Pulsar", &self.pulsar);
+        detail_row(ui, "Time", self.time.to_string());
+        detail_row(ui, "Error", self.error.to_string());
+        detail_row(ui, "Frequency", self.frequency.to_string());
+        detail_row(ui, "Process ID", self.process.to_string());
+        detail_row(ui, "Observer ID", self.observer.to_string());
+        detail_row(ui, "Template ID", self.template.to_string());
     }
 
-    fn format(&self, row: &mut egui_extras::TableRow) {
-        row.col(|ui| {
-            ui.label(self.process.to_string());
-        });
-        row.col(|ui| {
-            ui.label(&self.pulsar);
-        });
-        row.col(|ui| {
-            ui.label(self.error.to_string());
-        });
-        row.col(|ui| {
-            ui.label(self.time.to_string());
-        });
-        row.col(|ui| {
-            ui.label(self.template.to_string());
-        });
-        row.col(|ui| {
-            ui.label(self.observer.to_string());
-        });
-        row.col(|ui| {
-            ui.label(self.frequency.to_string());
-        });
+    fn related(&self, related: &Self::Related, ui: &mut egui::Ui) {
+        egui::Grid::new("toa_related_grid")
+            .num_columns(2)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                let process = &related.process;
+                detail_row(
+                    ui,
+                    "Process",
+                    format!(
+                        "#{}, {} TOA(s)",
+                        self.process, related.process_toas
+                    ),
+                );
+                detail_row(ui, "Method", &process.method);
+                detail_row(ui, "Started", &process.started_at);
+                detail_row(ui, "Launched by", &process.user);
+                detail_row(
+                    ui,
+                    "Process .par",
+                    process
+                        .par_id
+                        .map_or_else(|| "None".into(), |id| format!("#{id}")),
+                );
+                detail_row(
+                    ui,
+                    "Process template",
+                    format!("#{}", process.template_id),
+                );
+                detail_row(ui, "Template", &related.template.file_path);
+                detail_row(
+                    ui,
+                    "Observer",
+                    format!(
+                        "#{}, {} TOA(s)",
+                        self.observer, related.observer_toas
+                    ),
+                );
+            });
     }
 }
 
 pub struct TOAsApp {
     pub downloader: Downloader<TOAData>,
     messages: Vec<StatusMessage>,
+
+    move_to_pulsar_id: Option<i32>,
 }
 impl TOAsApp {
     pub const fn new() -> Self {
         Self {
             downloader: Downloader::new(),
             messages: Vec::new(),
+
+            move_to_pulsar_id: None,
         }
     }
 
     pub fn show(&mut self, ctx: &egui::Context, archivist: &Syncher) {
         self.downloader.action_bar(ctx);
+        self.downloader.detail_panel(ctx);
 
         match self.downloader.action() {
-            DownloaderAction::None => {}
+            DownloaderAction::None | DownloaderAction::Edit { .. } => {}
             DownloaderAction::Delete(index) => match index {
                 Some(id) => {
                     archivist.request(Request::DeleteItem(DATA_TYPE, id));
@@ -106,6 +217,19 @@ impl TOAsApp {
             DownloaderAction::Download(ft) => {
                 archivist.request(Request::Download(DATA_TYPE, ft));
             }
+
+            DownloaderAction::Link(id) => {
+                let item = self.downloader.get(id);
+                self.move_to_pulsar_id = item.map(|d| d.pulsar_id);
+            }
+
+            DownloaderAction::Related(id) => {
+                archivist.request(Request::Related(DATA_TYPE, id));
+            }
+
+            DownloaderAction::Resync => {
+                archivist.request(Request::Resync(DATA_TYPE));
+            }
         }
 
         let response = egui::CentralPanel::default()
@@ -123,10 +247,18 @@ impl TOAsApp {
         }
     }
 
+    pub fn reset_ui(&mut self) {
+        self.downloader.stop_fetching();
+    }
+
     pub fn deselect(&mut self) {
         self.downloader.deselect();
     }
 
+    pub(crate) const fn select_pulsar(&mut self) -> Option<i32> {
+        self.move_to_pulsar_id.take()
+    }
+
     fn body(&mut self, ui: &mut egui::Ui) {
         ui.heading(RichText::new("TOAs").strong());
         ui.add_space(12.0);